@@ -0,0 +1,123 @@
+//! A second program that calls `program` via CPI.
+//!
+//! Each instruction wraps one of `program`'s instructions,
+//! calling it with the wrappers in `program::cpi`
+//! and passing its own accounts through,
+//! so the transaction's signers stay signers in `program`.
+
+use borsh::de::BorshDeserialize;
+use common::{CallerInstruction, ReturnData, Secp256k1RecoverReturnData};
+use program::cpi;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint,
+    entrypoint::ProgramResult,
+    msg,
+    program::{get_return_data, set_return_data},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+entrypoint!(process_instruction);
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let instruction = {
+        let mut instruction_data = instruction_data;
+        CallerInstruction::deserialize(&mut instruction_data)?
+    };
+
+    let account_info_iter = &mut accounts.iter();
+    let callee_program_account = next_account_info(account_info_iter)?;
+    assert!(callee_program_account.executable);
+    assert_ne!(callee_program_account.key, program_id);
+
+    match instruction {
+        CallerInstruction::PrintSysvars(instr) => {
+            msg!("caller: print sysvars");
+
+            let cpi_accounts = cpi::PrintSysvarsAccounts {
+                system_program: next_account_info(account_info_iter)?.clone(),
+                clock: next_account_info(account_info_iter)?.clone(),
+                epoch_schedule: next_account_info(account_info_iter)?.clone(),
+                instructions: next_account_info(account_info_iter)?.clone(),
+                rent: next_account_info(account_info_iter)?.clone(),
+                slot_hashes: next_account_info(account_info_iter)?.clone(),
+                slot_history: next_account_info(account_info_iter)?.clone(),
+                stake_history: next_account_info(account_info_iter)?.clone(),
+            };
+
+            cpi::print_sysvars(callee_program_account, instr, cpi_accounts, &[])?;
+        }
+        CallerInstruction::Secp256k1Recover(instr) => {
+            msg!("caller: secp256k1 recover");
+
+            cpi::demo_secp256k1_recover(callee_program_account, instr, &[])?;
+
+            forward_recovered_pubkey(callee_program_account.key)?;
+        }
+        CallerInstruction::CreateAccount(instr) => {
+            msg!("caller: create account");
+
+            let system_program_account = next_account_info(account_info_iter)?;
+            let payer = next_account_info(account_info_iter)?;
+            let new_account_pda = next_account_info(account_info_iter)?;
+            assert_eq!(*payer.key, instr.payer);
+            assert_eq!(*new_account_pda.key, instr.new_account_pda);
+
+            // The payer signed the transaction, not this program,
+            // but is still a signer when passed on to `program`
+            // and from there to the system program.
+            msg!("payer {} is signer: {}", payer.key, payer.is_signer);
+
+            let cpi_accounts = cpi::SystemProgramAccounts {
+                system_program: system_program_account.clone(),
+                payer: payer.clone(),
+                new_account: new_account_pda.clone(),
+            };
+
+            cpi::demo_system_program_create_account(
+                callee_program_account,
+                instr,
+                cpi_accounts,
+                &[],
+            )?;
+
+            msg!(
+                "new account {} owner: {}",
+                new_account_pda.key,
+                new_account_pda.owner
+            );
+            if new_account_pda.owner != callee_program_account.key {
+                return Err(ProgramError::IllegalOwner);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the return data was set by `program`, not us,
+/// then return it as our own.
+fn forward_recovered_pubkey(callee_program_id: &Pubkey) -> ProgramResult {
+    let (return_data_pubkey, return_data) = match get_return_data() {
+        Some(return_data) => return_data,
+        None => {
+            msg!("expected return data");
+            return Err(ProgramError::InvalidAccountData);
+        }
+    };
+
+    msg!("return data set by {}", return_data_pubkey);
+    assert_eq!(&return_data_pubkey, callee_program_id);
+
+    let recovered = Secp256k1RecoverReturnData::decode_return_data(&return_data)?;
+    msg!("recovered pubkey: {:?}", recovered.pubkey);
+
+    set_return_data(&recovered.encode_return_data()?);
+
+    Ok(())
+}
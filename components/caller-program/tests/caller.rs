@@ -0,0 +1,152 @@
+//! Run `caller-program` against `program`, both loaded as native processors.
+
+use common::{
+    AccountState, CallerInstruction, DemoSecp256k1RecoverInstruction,
+    DemoSystemProgramCreateAccountInstruction,
+};
+use solana_program::{instruction::InstructionError, keccak, pubkey::Pubkey};
+use solana_program_test::{processor, BanksClient, ProgramTest};
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+
+/// The key the client signs secp256k1 messages with,
+/// whose pubkey `program` accepts.
+const AUTHORIZED_SECRET_KEY: [u8; 32] = [
+    0x1E, 0xC2, 0xD4, 0x0F, 0x18, 0x08, 0xD7, 0xE7, 0xA3, 0x23, 0x1B, 0xD8, 0x14, 0x7F, 0x24, 0x66,
+    0x6B, 0xBB, 0xD3, 0xA1, 0xA2, 0xCF, 0x39, 0xF3, 0x97, 0xF3, 0x05, 0x15, 0xAB, 0x13, 0xCC, 0xC6,
+];
+
+struct Programs {
+    caller_program_id: Pubkey,
+    program_id: Pubkey,
+    banks_client: BanksClient,
+    payer: Keypair,
+}
+
+async fn start() -> Programs {
+    let caller_program_id = Pubkey::new_unique();
+    let program_id = Pubkey::new_unique();
+
+    let mut program_test = ProgramTest::new(
+        "caller_program",
+        caller_program_id,
+        processor!(caller_program::process_instruction),
+    );
+    program_test.add_program(
+        "program",
+        program_id,
+        processor!(program::process_instruction),
+    );
+
+    let (banks_client, payer, _) = program_test.start().await;
+
+    Programs {
+        caller_program_id,
+        program_id,
+        banks_client,
+        payer,
+    }
+}
+
+impl Programs {
+    async fn call(&mut self, instr: CallerInstruction) -> Result<(), TransactionError> {
+        let instr = instr.build_instruction(&self.caller_program_id, &self.program_id);
+        let blockhash = self.banks_client.get_latest_blockhash().await.unwrap();
+        let tx = Transaction::new_signed_with_payer(
+            &[instr],
+            Some(&self.payer.pubkey()),
+            &[&self.payer],
+            blockhash,
+        );
+
+        self.banks_client
+            .process_transaction(tx)
+            .await
+            .map_err(|e| e.unwrap())
+    }
+}
+
+fn recover_instruction(secret_key: &[u8; 32]) -> DemoSecp256k1RecoverInstruction {
+    let secret_key = libsecp256k1::SecretKey::parse(secret_key).unwrap();
+
+    let message = b"hello world";
+    let message_hash = keccak::hash(message);
+
+    let secp_message = libsecp256k1::Message::parse(&message_hash.0);
+    let (signature, recovery_id) = libsecp256k1::sign(&secp_message, &secret_key);
+
+    DemoSecp256k1RecoverInstruction {
+        message: message.to_vec(),
+        signature: signature.serialize(),
+        recovery_id: recovery_id.serialize(),
+    }
+}
+
+#[tokio::test]
+async fn create_account() {
+    let mut programs = start().await;
+    let payer = programs.payer.pubkey();
+
+    let new_account_seed = [7; 16];
+    let (new_account_pda, new_account_bump_seed) =
+        Pubkey::find_program_address(&[payer.as_ref(), &new_account_seed], &programs.program_id);
+
+    programs
+        .call(CallerInstruction::CreateAccount(
+            DemoSystemProgramCreateAccountInstruction {
+                payer,
+                new_account_pda,
+                new_account_seed,
+                new_account_bump_seed,
+            },
+        ))
+        .await
+        .unwrap();
+
+    let account = programs
+        .banks_client
+        .get_account(new_account_pda)
+        .await
+        .unwrap()
+        .expect("account created");
+    assert_eq!(account.owner, programs.program_id);
+
+    let state = AccountState::decode(&account.data).unwrap();
+    assert_eq!(state.creator, payer);
+}
+
+/// `caller-program` only succeeds if `program` set the return data,
+/// which it then returns as its own.
+#[tokio::test]
+async fn secp256k1_recover_returns_data() {
+    let mut programs = start().await;
+
+    programs
+        .call(CallerInstruction::Secp256k1Recover(recover_instruction(
+            &AUTHORIZED_SECRET_KEY,
+        )))
+        .await
+        .unwrap();
+}
+
+/// An error from `program` fails the caller's instruction.
+#[tokio::test]
+async fn secp256k1_recover_unauthorized() {
+    let mut programs = start().await;
+
+    let result = programs
+        .call(CallerInstruction::Secp256k1Recover(recover_instruction(
+            &[1; 32],
+        )))
+        .await;
+
+    assert_eq!(
+        result,
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::InvalidArgument
+        ))
+    );
+}
@@ -0,0 +1,367 @@
+use crate::logs;
+use crate::output::{Report, TransactionReport};
+use crate::util::Config;
+use crate::{
+    caller, ed25519, introspect, invoke, policies, secp256k1, state, sysprog, sysvar_access,
+    sysvars,
+};
+use anyhow::{bail, Context, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use std::collections::BTreeMap;
+use std::fs::File;
+
+#[derive(clap::Args)]
+pub struct BenchOpts {
+    /// Times to run each demo.
+    #[clap(long, default_value = "5")]
+    pub iterations: usize,
+    /// Only run these demos, by command name, e.g. `demo-invoke`.
+    ///
+    /// `demo-caller-program` needs `caller-program` deployed,
+    /// so only runs when named here.
+    #[clap(long)]
+    pub demo: Vec<String>,
+    /// Fail if any operation's median exceeds the median in this baseline file.
+    #[clap(long)]
+    pub baseline: Option<String>,
+    /// Percentage an operation may exceed its baseline by.
+    #[clap(long, default_value = "0")]
+    pub tolerance: f64,
+    /// Write the results to this file, for use as a baseline.
+    #[clap(long)]
+    pub save_baseline: Option<String>,
+}
+
+type Demo = fn(&Config, &RpcClient) -> Result<Vec<TransactionReport>>;
+
+/// Every demo that calls the program, by command name.
+const DEMOS: &[(&str, Demo)] = &[
+    ("print-sysvars-via-program", |config, client| {
+        let opts = sysvars::PrintSysvarsOpts {
+            single_transaction: false,
+            compute_units: None,
+            heap_frame: None,
+        };
+        Ok(sysvars::print_sysvars_via_program(config, client, opts)?.transactions)
+    }),
+    ("print-sysvars-single-transaction", |config, client| {
+        let opts = sysvars::PrintSysvarsOpts {
+            single_transaction: true,
+            compute_units: None,
+            heap_frame: None,
+        };
+        Ok(sysvars::print_sysvars_via_program(config, client, opts)?.transactions)
+    }),
+    ("compare-sysvar-access", |config, client| {
+        Ok(sysvar_access::compare_sysvar_access(config, client)?.transactions)
+    }),
+    ("demo-secp256k1-verify-basic", |config, client| {
+        Ok(secp256k1::demo_secp256k1_verify_basic(config, client)?.transactions)
+    }),
+    ("demo-secp256k1-custom-many", |config, client| {
+        Ok(vec![
+            secp256k1::demo_secp256k1_custom_many(config, client)?.transaction,
+        ])
+    }),
+    ("demo-secp256k1-recover", |config, client| {
+        Ok(secp256k1::demo_secp256k1_recover(config, client)?.transactions)
+    }),
+    ("demo-ed25519", |config, client| {
+        Ok(ed25519::demo_ed25519_instruction(config, client)?.transactions)
+    }),
+    ("demo-invoke", |config, client| {
+        Ok(invoke::demo_invoke(config, client)?.transactions)
+    }),
+    ("demo-cpi-guard", |config, client| {
+        Ok(invoke::demo_cpi_guard(config, client)?.transactions)
+    }),
+    ("demo-invoke-depth", |config, client| {
+        Ok(invoke::demo_invoke_depth(config, client)?.transactions)
+    }),
+    ("demo-introspect-instructions", |config, client| {
+        Ok(introspect::demo_introspect_instructions(config, client)?.transactions)
+    }),
+    ("demo-transaction-policies", |config, client| {
+        Ok(policies::demo_transaction_policies(config, client)?.transactions)
+    }),
+    (
+        "demo-system-program-create-account-cpi",
+        |config, client| {
+            Ok(vec![
+                sysprog::demo_system_program_create_account_cpi(config, client)?.transaction,
+            ])
+        },
+    ),
+    (
+        "demo-system-program-transfer-alloc-assign-cpi",
+        |config, client| {
+            Ok(vec![
+                sysprog::demo_system_program_transfer_alloc_assign_cpi(config, client)?.transaction,
+            ])
+        },
+    ),
+    (
+        "demo-system-program-create-account-with-seed-cpi",
+        |config, client| {
+            Ok(vec![
+                sysprog::demo_system_program_create_account_with_seed_cpi(config, client)?
+                    .transaction,
+            ])
+        },
+    ),
+    (
+        "demo-system-program-allocate-with-seed-cpi",
+        |config, client| {
+            Ok(vec![
+                sysprog::demo_system_program_allocate_with_seed_cpi(config, client)?.transaction,
+            ])
+        },
+    ),
+    (
+        "demo-system-program-assign-with-seed-cpi",
+        |config, client| {
+            Ok(vec![
+                sysprog::demo_system_program_assign_with_seed_cpi(config, client)?.transaction,
+            ])
+        },
+    ),
+    (
+        "demo-system-program-transfer-with-seed-cpi",
+        |config, client| {
+            Ok(vec![
+                sysprog::demo_system_program_transfer_with_seed_cpi(config, client)?.transaction,
+            ])
+        },
+    ),
+    ("demo-system-program-nonce-cpi", |config, client| {
+        Ok(sysprog::demo_system_program_nonce_cpi(config, client)?.transactions)
+    }),
+    ("demo-system-program-prefunded-cpi", |config, client| {
+        Ok(sysprog::demo_system_program_prefunded_cpi(config, client)?.transactions)
+    }),
+    ("demo-account-state", |config, client| {
+        Ok(state::demo_account_state(config, client)?.transactions)
+    }),
+    ("demo-create-state-accounts", |config, client| {
+        let opts = state::CreateStateAccountsOpts { count: None };
+        Ok(vec![
+            state::demo_create_state_accounts(config, client, &opts)?.transaction,
+        ])
+    }),
+];
+
+/// Demos that need more than the program deployed,
+/// so only run when named with `--demo`.
+const OPTIONAL_DEMOS: &[(&str, Demo)] = &[("demo-caller-program", |config, client| {
+    let opts = caller::CallerProgramOpts {
+        caller_program_id: None,
+    };
+    Ok(caller::demo_caller_program(config, client, &opts)?.transactions)
+})];
+
+/// Run each demo repeatedly and collect the compute units
+/// of every program invocation and every checkpoint it logs.
+///
+/// This runs against a validator, or with `--simulate`, against simulation.
+///
+/// Each run of a demo that creates an account leaves a state account behind,
+/// holding its rent, as when the demo is run on its own.
+/// `cleanup` closes them.
+pub fn bench(config: &Config, client: &RpcClient, opts: BenchOpts) -> Result<BenchReport> {
+    let all_demos = || DEMOS.iter().chain(OPTIONAL_DEMOS);
+
+    for name in &opts.demo {
+        if !all_demos().any(|(demo, _)| demo == name) {
+            bail!("unknown demo {}", name);
+        }
+    }
+
+    let program_id = config.program_id().to_string();
+    let mut samples: BTreeMap<String, Vec<u64>> = BTreeMap::new();
+
+    for (name, demo) in all_demos() {
+        let selected = if opts.demo.is_empty() {
+            DEMOS.iter().any(|(demo, _)| demo == name)
+        } else {
+            opts.demo.iter().any(|demo| demo == name)
+        };
+        if !selected {
+            continue;
+        }
+
+        for iteration in 0..opts.iterations {
+            info!("{} {}/{}", name, iteration + 1, opts.iterations);
+
+            // Identical transactions with the same blockhash would be rejected as duplicates.
+            if iteration > 0 && config.simulate.is_none() {
+                let blockhash = client.get_latest_blockhash()?;
+                client.get_new_latest_blockhash(&blockhash)?;
+            }
+
+            for transaction in demo(config, client)? {
+                let prefix = match &transaction.label {
+                    Some(label) => format!("{}/{}", name, label),
+                    None => name.to_string(),
+                };
+
+                for invocation in &transaction.invocations {
+                    if invocation.program_id != program_id {
+                        continue;
+                    }
+
+                    if let Some(units) = invocation.units_consumed {
+                        record(&mut samples, format!("{}/total", prefix), units);
+                    }
+
+                    let costs = logs::checkpoint_costs(&logs::checkpoints(invocation));
+                    for cost in costs {
+                        record(
+                            &mut samples,
+                            format!("{}/{}", prefix, cost.label),
+                            cost.units,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    let operations: BTreeMap<_, _> = samples
+        .into_iter()
+        .map(|(operation, samples)| (operation, Stats::new(samples)))
+        .collect();
+
+    let regressions = match &opts.baseline {
+        Some(path) => {
+            info!("comparing with baseline {}", path);
+            let file = File::open(path).with_context(|| format!("unable to open {}", path))?;
+            let baseline: Baseline = serde_json::from_reader(file)
+                .with_context(|| format!("unable to parse baseline {}", path))?;
+            compare_with_baseline(&operations, &baseline, opts.tolerance)
+        }
+        None => vec![],
+    };
+
+    if let Some(path) = &opts.save_baseline {
+        let file = File::create(path).with_context(|| format!("unable to create {}", path))?;
+        serde_json::to_writer_pretty(
+            file,
+            &Baseline {
+                operations: operations.clone(),
+            },
+        )?;
+        info!("saved baseline to {}", path);
+    }
+
+    Ok(BenchReport {
+        iterations: opts.iterations,
+        operations,
+        regressions,
+    })
+}
+
+fn record(samples: &mut BTreeMap<String, Vec<u64>>, operation: String, units: u64) {
+    samples.entry(operation).or_default().push(units);
+}
+
+fn compare_with_baseline(
+    operations: &BTreeMap<String, Stats>,
+    baseline: &Baseline,
+    tolerance: f64,
+) -> Vec<Regression> {
+    operations
+        .iter()
+        .filter_map(|(operation, stats)| {
+            let baseline = baseline.operations.get(operation)?;
+            let limit = baseline.median as f64 * (1.0 + tolerance / 100.0);
+            (stats.median as f64 > limit).then(|| Regression {
+                operation: operation.clone(),
+                baseline: baseline.median,
+                median: stats.median,
+            })
+        })
+        .collect()
+}
+
+/// The format of `--baseline` and `--save-baseline` files.
+#[derive(Serialize, Deserialize)]
+struct Baseline {
+    operations: BTreeMap<String, Stats>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Stats {
+    pub samples: usize,
+    pub min: u64,
+    pub median: u64,
+    pub max: u64,
+}
+
+impl Stats {
+    fn new(mut samples: Vec<u64>) -> Stats {
+        samples.sort_unstable();
+        Stats {
+            samples: samples.len(),
+            min: samples[0],
+            median: samples[samples.len() / 2],
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Regression {
+    pub operation: String,
+    pub baseline: u64,
+    pub median: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BenchReport {
+    pub iterations: usize,
+    /// Compute units by `<demo>[/<transaction>]/<checkpoint>`,
+    /// where `total` is all the units used by the program's instruction.
+    pub operations: BTreeMap<String, Stats>,
+    /// Operations whose median exceeded the baseline.
+    pub regressions: Vec<Regression>,
+}
+
+impl Report for BenchReport {
+    fn print_text(&self) {
+        let width = self
+            .operations
+            .keys()
+            .map(String::len)
+            .max()
+            .unwrap_or(0)
+            .max("operation".len());
+
+        println!(
+            "{:<width$} {:>8} {:>8} {:>8}",
+            "operation",
+            "min",
+            "median",
+            "max",
+            width = width
+        );
+        for (operation, stats) in &self.operations {
+            println!(
+                "{:<width$} {:>8} {:>8} {:>8}",
+                operation,
+                stats.min,
+                stats.median,
+                stats.max,
+                width = width
+            );
+        }
+
+        for regression in &self.regressions {
+            println!(
+                "regression: {}: median {} exceeds baseline {}",
+                regression.operation, regression.median, regression.baseline
+            );
+        }
+    }
+}
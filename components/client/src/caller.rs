@@ -0,0 +1,108 @@
+use crate::output::{self, Report, TransactionReport};
+use crate::secp256k1;
+use crate::util;
+use anyhow::Result;
+use common::{
+    CallerInstruction, DemoSystemProgramCreateAccountInstruction, PrintSysvarsInstruction,
+    PrintSysvarsSection,
+};
+use rand::Rng;
+use serde::Serialize;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signature::Signer, transaction::Transaction};
+
+#[derive(clap::Args)]
+pub struct CallerProgramOpts {
+    /// The id of `caller-program`, if not the one in `target/deploy`.
+    #[clap(long)]
+    pub caller_program_id: Option<Pubkey>,
+}
+
+/// Call `program` via `caller-program`,
+/// printing sysvars, recovering a secp256k1 pubkey, and creating a PDA.
+pub fn demo_caller_program(
+    config: &crate::util::Config,
+    client: &RpcClient,
+    opts: &CallerProgramOpts,
+) -> Result<CallerProgramReport> {
+    let caller_program_id = util::get_caller_program_id(client, opts.caller_program_id)?;
+    let program_id = config.program_id();
+    let payer = config.keypair().pubkey();
+
+    let new_account_seed: [u8; 16] = rand::thread_rng().gen();
+    let (new_account_pda, new_account_bump_seed) =
+        Pubkey::find_program_address(&[payer.as_ref(), &new_account_seed], program_id);
+
+    let instrs = vec![
+        (
+            "print sysvars",
+            CallerInstruction::PrintSysvars(PrintSysvarsInstruction {
+                section: PrintSysvarsSection::Two,
+            }),
+        ),
+        (
+            "secp256k1 recover",
+            CallerInstruction::Secp256k1Recover(secp256k1::recover_instruction()?),
+        ),
+        (
+            "create account",
+            CallerInstruction::CreateAccount(DemoSystemProgramCreateAccountInstruction {
+                payer,
+                new_account_pda,
+                new_account_seed,
+                new_account_bump_seed,
+            }),
+        ),
+    ];
+
+    let mut transactions = vec![];
+
+    for (label, instr) in instrs {
+        let instr = instr.build_instruction(&caller_program_id, program_id);
+
+        let blockhash = client.get_latest_blockhash()?;
+        let tx = Transaction::new_signed_with_payer(
+            &[instr],
+            Some(&payer),
+            &[config.keypair()],
+            blockhash,
+        );
+
+        let transaction = util::send_and_confirm(config, client, &tx)?;
+        transactions.push(transaction.with_label(label));
+    }
+
+    // `caller-program` returns the pubkey `program` recovered.
+    let recovered_pubkey = secp256k1::check_recovered_pubkey(&transactions[1])?;
+
+    Ok(CallerProgramReport {
+        caller_program_id,
+        transactions,
+        recovered_pubkey: hex::encode(recovered_pubkey),
+        new_account: new_account_pda,
+    })
+}
+
+#[derive(Serialize)]
+pub struct CallerProgramReport {
+    #[serde(serialize_with = "output::display")]
+    pub caller_program_id: Pubkey,
+    pub transactions: Vec<TransactionReport>,
+    /// The public key returned via `caller-program`, hex encoded.
+    pub recovered_pubkey: String,
+    /// The PDA `program` created for `caller-program`.
+    #[serde(serialize_with = "output::display")]
+    pub new_account: Pubkey,
+}
+
+impl Report for CallerProgramReport {
+    fn print_text(&self) {
+        println!("caller program: {}", self.caller_program_id);
+        for transaction in &self.transactions {
+            transaction.print_text();
+        }
+
+        println!("recovered pubkey: {}", self.recovered_pubkey);
+        println!("new account: {}", self.new_account);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::output::{Report, TransactionReport};
+use crate::util::{self, Config};
+use anyhow::Result;
+use common::{AccountState, CloseAccountsInstruction};
+use log::info;
+use serde::Serialize;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
+};
+use solana_sdk::{bs58, pubkey::Pubkey, signature::Signer, transaction::Transaction};
+
+/// Accounts closed per transaction.
+///
+/// Each adds a 32-byte key to the message and to the instruction data,
+/// so this stays well under the transaction size limit.
+const CLOSE_BATCH_SIZE: usize = 10;
+
+/// Find every state account the wallet created, and close them.
+///
+/// Accounts are only reported as closed once their transaction is confirmed,
+/// so when simulating none are.
+///
+/// Accounts without an `AccountState` aren't found,
+/// since nothing records who may reclaim them.
+/// That's why the assign-with-seed demo closes its empty account itself.
+pub fn cleanup(config: &Config, client: &RpcClient) -> Result<CleanupReport> {
+    let payer = config.keypair().pubkey();
+
+    let accounts = find_created_accounts(config, client, &payer)?;
+    info!("found {} accounts to close", accounts.len());
+
+    let mut closed = vec![];
+    let mut reclaimed_lamports = 0;
+    let mut transactions = vec![];
+    for batch in accounts.chunks(CLOSE_BATCH_SIZE) {
+        let instr = CloseAccountsInstruction {
+            payer,
+            accounts: batch.iter().map(|(pubkey, _)| *pubkey).collect(),
+        }
+        .build_instruction(config.program_id());
+
+        let blockhash = client.get_latest_blockhash()?;
+
+        let tx = Transaction::new_signed_with_payer(
+            &[instr],
+            Some(&payer),
+            &[config.keypair()],
+            blockhash,
+        );
+
+        transactions.push(util::send_and_confirm(config, client, &tx)?);
+
+        if config.simulate.is_none() {
+            for (pubkey, lamports) in batch {
+                closed.push(pubkey.to_string());
+                reclaimed_lamports += lamports;
+            }
+        }
+    }
+
+    Ok(CleanupReport {
+        found: accounts.len(),
+        closed,
+        reclaimed_lamports,
+        transactions,
+    })
+}
+
+/// The program's accounts whose data is an `AccountState` naming `creator`,
+/// with their lamports.
+fn find_created_accounts(
+    config: &Config,
+    client: &RpcClient,
+    creator: &Pubkey,
+) -> Result<Vec<(Pubkey, u64)>> {
+    let filters = vec![
+        RpcFilterType::Memcmp(Memcmp {
+            offset: AccountState::DISCRIMINATOR_OFFSET,
+            bytes: MemcmpEncodedBytes::Base58(
+                bs58::encode(AccountState::DISCRIMINATOR).into_string(),
+            ),
+            encoding: None,
+        }),
+        RpcFilterType::Memcmp(Memcmp {
+            offset: AccountState::CREATOR_OFFSET,
+            bytes: MemcmpEncodedBytes::Base58(creator.to_string()),
+            encoding: None,
+        }),
+    ];
+    let rpc_config = RpcProgramAccountsConfig {
+        filters: Some(filters),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(config.commitment),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+
+    let accounts = client.get_program_accounts_with_config(config.program_id(), rpc_config)?;
+
+    Ok(accounts
+        .into_iter()
+        .map(|(pubkey, account)| (pubkey, account.lamports))
+        .collect())
+}
+
+#[derive(Serialize, Debug)]
+pub struct CleanupReport {
+    /// How many accounts there were to close.
+    pub found: usize,
+    /// The accounts closed by confirmed transactions.
+    pub closed: Vec<String>,
+    pub reclaimed_lamports: u64,
+    pub transactions: Vec<TransactionReport>,
+}
+
+impl Report for CleanupReport {
+    fn print_text(&self) {
+        for account in &self.closed {
+            println!("closed {}", account);
+        }
+        println!(
+            "found {} accounts, closed {}, reclaimed {} lamports",
+            self.found,
+            self.closed.len(),
+            self.reclaimed_lamports
+        );
+        for transaction in &self.transactions {
+            transaction.print_text();
+        }
+    }
+}
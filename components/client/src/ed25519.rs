@@ -1,11 +1,9 @@
+use crate::output::TransactionsReport;
+use crate::util;
 use anyhow::Result;
 use common::DemoEd25519Instruction;
 use solana_client::rpc_client::RpcClient;
-use solana_sdk::{
-    ed25519_instruction,
-    signature::{Keypair, Signer},
-    transaction::Transaction,
-};
+use solana_sdk::{ed25519_instruction, signature::Signer, transaction::Transaction};
 
 use ed25519_dalek::{Keypair as Ed25519Keypair, Signer as Ed25519Signer, KEYPAIR_LENGTH};
 
@@ -19,27 +17,25 @@ const ED25519_KEYPAIR: [u8; KEYPAIR_LENGTH] = [
 pub fn demo_ed25519_instruction(
     config: &crate::util::Config,
     client: &RpcClient,
-    program_keypair: &Keypair,
-) -> Result<()> {
+) -> Result<TransactionsReport> {
     let message: &[u8] = b"This is a demo message.";
 
     let keypair = Ed25519Keypair::from_bytes(&ED25519_KEYPAIR)?;
     let _signature = keypair.sign(message);
 
     let ed25519_instr = ed25519_instruction::new_ed25519_instruction(&keypair, message);
-    let program_instr = DemoEd25519Instruction.build_instruction(&program_keypair.pubkey());
+    let program_instr = DemoEd25519Instruction.build_instruction(config.program_id());
 
     let blockhash = client.get_latest_blockhash()?;
 
     let tx = Transaction::new_signed_with_payer(
         &[ed25519_instr, program_instr],
-        Some(&config.keypair.pubkey()),
-        &[&config.keypair],
+        Some(&config.keypair().pubkey()),
+        &[config.keypair()],
         blockhash,
     );
 
-    let sig = client.send_and_confirm_transaction(&tx)?;
-    println!("sig: {}", sig);
+    let transaction = util::send_and_confirm(config, client, &tx)?;
 
-    Ok(())
+    Ok(transaction.into())
 }
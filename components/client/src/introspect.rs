@@ -0,0 +1,77 @@
+use crate::output::TransactionsReport;
+use crate::util;
+use anyhow::Result;
+use common::{IntrospectInstructionsInstruction, ReadSysvarsInstruction, SysvarAccess};
+use ed25519_dalek::Keypair as Ed25519Keypair;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    ed25519_instruction, instruction::Instruction, secp256k1_instruction, signature::Signer,
+    system_instruction, transaction::Transaction,
+};
+
+/// Send transactions mixing precompile, system program and program instructions,
+/// each with an instruction that logs every instruction in its transaction.
+pub fn demo_introspect_instructions(
+    config: &crate::util::Config,
+    client: &RpcClient,
+) -> Result<TransactionsReport> {
+    let payer = config.keypair().pubkey();
+
+    let secp256k1_instr = {
+        let secret_key = libsecp256k1::SecretKey::random(&mut rand::thread_rng());
+        secp256k1_instruction::new_secp256k1_instruction(&secret_key, b"hello secp256k1")
+    };
+    let ed25519_instr = {
+        let keypair = Ed25519Keypair::generate(&mut rand::thread_rng());
+        ed25519_instruction::new_ed25519_instruction(&keypair, b"hello ed25519")
+    };
+    let transfer_instr = system_instruction::transfer(&payer, &payer, 1);
+    let read_sysvars_instr = ReadSysvarsInstruction {
+        access: SysvarAccess::Syscalls,
+    }
+    .build_instruction(config.program_id());
+    let introspect_instr = IntrospectInstructionsInstruction.build_instruction(config.program_id());
+
+    let transactions: Vec<(&str, Vec<Instruction>)> = vec![
+        (
+            "introspect last",
+            vec![
+                secp256k1_instr.clone(),
+                ed25519_instr.clone(),
+                transfer_instr.clone(),
+                read_sysvars_instr.clone(),
+                introspect_instr.clone(),
+            ],
+        ),
+        // `new_secp256k1_instruction` expects to be the first instruction.
+        (
+            "introspect second",
+            vec![
+                secp256k1_instr,
+                introspect_instr,
+                transfer_instr,
+                read_sysvars_instr,
+                ed25519_instr,
+            ],
+        ),
+    ];
+
+    let mut reports = vec![];
+
+    for (label, instrs) in transactions {
+        let blockhash = client.get_latest_blockhash()?;
+        let tx = Transaction::new_signed_with_payer(
+            &instrs,
+            Some(&payer),
+            &[config.keypair()],
+            blockhash,
+        );
+
+        let transaction = util::send_and_confirm(config, client, &tx)?;
+        reports.push(transaction.with_label(label));
+    }
+
+    Ok(TransactionsReport {
+        transactions: reports,
+    })
+}
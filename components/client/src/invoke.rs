@@ -1,31 +1,244 @@
-use anyhow::Result;
-use common::{DemoInvokeInstruction, DemoInvokeMode};
+use crate::output::{Report, TransactionReport, TransactionsReport};
+use crate::util::{self, SimulateConfig};
+use anyhow::{bail, Result};
+use common::{DemoInvokeInstruction, DemoInvokeMode, InvokeReturnData, RecursiveReturnData};
+use serde::Serialize;
 use solana_client::rpc_client::RpcClient;
-use solana_sdk::{
-    signature::{Keypair, Signer},
-    transaction::Transaction,
-};
+use solana_sdk::{signature::Signer, transaction::Transaction};
 
-pub fn demo_invoke(
-    config: &crate::util::Config,
-    client: &RpcClient,
-    program_keypair: &Keypair,
-) -> Result<()> {
+/// The runtime's max invoke depth, counting the top-level instruction.
+const MAX_INVOKE_DEPTH: u8 = 4;
+
+pub fn demo_invoke(config: &crate::util::Config, client: &RpcClient) -> Result<InvokeReport> {
     let instr = DemoInvokeInstruction {
         mode: DemoInvokeMode::Caller,
     }
-    .build_instruction(&program_keypair.pubkey());
+    .build_instruction(config.program_id());
 
     let blockhash = client.get_latest_blockhash()?;
     let tx = Transaction::new_signed_with_payer(
         &[instr],
-        Some(&config.keypair.pubkey()),
-        &[&config.keypair],
+        Some(&config.keypair().pubkey()),
+        &[config.keypair()],
+        blockhash,
+    );
+
+    let transaction = util::send_and_confirm(config, client, &tx)?;
+
+    let return_data: Option<InvokeReturnData> = match transaction.invocations.first() {
+        Some(invocation) => invocation.decode_return_data()?,
+        None => None,
+    };
+    let message = match return_data {
+        Some(return_data) => return_data.message,
+        None => bail!("expected return data"),
+    };
+
+    Ok(InvokeReport {
+        transactions: vec![transaction],
+        message,
+    })
+}
+
+#[derive(Serialize)]
+pub struct InvokeReport {
+    pub transactions: Vec<TransactionReport>,
+    /// The message the callee returned.
+    pub message: String,
+}
+
+impl Report for InvokeReport {
+    fn print_text(&self) {
+        for transaction in &self.transactions {
+            transaction.print_text();
+        }
+
+        println!("returned: {:?}", self.message);
+    }
+}
+
+/// Call the guarded instruction at the top level, which succeeds,
+/// then via CPI, which the guard rejects.
+///
+/// The rejected transaction is only simulated, to get its logs.
+pub fn demo_cpi_guard(
+    config: &crate::util::Config,
+    client: &RpcClient,
+) -> Result<TransactionsReport> {
+    let guarded_instr = DemoInvokeInstruction {
+        mode: DemoInvokeMode::Guarded,
+    }
+    .build_instruction(config.program_id());
+
+    let blockhash = client.get_latest_blockhash()?;
+    let tx = Transaction::new_signed_with_payer(
+        &[guarded_instr],
+        Some(&config.keypair().pubkey()),
+        &[config.keypair()],
+        blockhash,
+    );
+
+    let top_level = util::send_and_confirm(config, client, &tx)?;
+
+    let caller_instr = DemoInvokeInstruction {
+        mode: DemoInvokeMode::CallerOfGuarded,
+    }
+    .build_instruction(config.program_id());
+
+    let tx = Transaction::new_signed_with_payer(
+        &[caller_instr],
+        Some(&config.keypair().pubkey()),
+        &[config.keypair()],
+        blockhash,
+    );
+
+    let simulate = SimulateConfig {
+        sig_verify: false,
+        replace_blockhash: true,
+    };
+    let via_cpi = util::simulate_transaction(client, &tx, simulate)?;
+
+    let rejected = via_cpi
+        .simulation
+        .as_ref()
+        .map_or(false, |simulation| simulation.err.is_some());
+    if !rejected {
+        bail!("the guarded instruction did not reject the CPI");
+    }
+
+    Ok(TransactionsReport {
+        transactions: vec![
+            top_level.with_label("top-level"),
+            via_cpi.with_label("via CPI"),
+        ],
+    })
+}
+
+/// Invoke recursively to the runtime's max depth, then one level past it,
+/// which is only simulated, then show how return data behaves across CPIs.
+pub fn demo_invoke_depth(
+    config: &crate::util::Config,
+    client: &RpcClient,
+) -> Result<InvokeDepthReport> {
+    let recursive_instr = |max_depth| {
+        DemoInvokeInstruction {
+            mode: DemoInvokeMode::Recursive {
+                depth: 1,
+                max_depth,
+            },
+        }
+        .build_instruction(config.program_id())
+    };
+
+    let blockhash = client.get_latest_blockhash()?;
+    let tx = Transaction::new_signed_with_payer(
+        &[recursive_instr(MAX_INVOKE_DEPTH)],
+        Some(&config.keypair().pubkey()),
+        &[config.keypair()],
         blockhash,
     );
 
-    let sig = client.send_and_confirm_transaction(&tx)?;
-    println!("sig: {}", sig);
+    let max_depth = util::send_and_confirm(config, client, &tx)?;
 
-    Ok(())
+    let tx = Transaction::new_signed_with_payer(
+        &[recursive_instr(MAX_INVOKE_DEPTH + 1)],
+        Some(&config.keypair().pubkey()),
+        &[config.keypair()],
+        blockhash,
+    );
+
+    let simulate = SimulateConfig {
+        sig_verify: false,
+        replace_blockhash: true,
+    };
+    let past_max_depth = util::simulate_transaction(client, &tx, simulate)?;
+
+    let depth_error = past_max_depth
+        .simulation
+        .as_ref()
+        .and_then(|simulation| simulation.err.clone());
+    if depth_error.is_none() {
+        bail!("invoking past the max depth did not fail");
+    }
+
+    let return_data_instr = DemoInvokeInstruction {
+        mode: DemoInvokeMode::ReturnDataCaller,
+    }
+    .build_instruction(config.program_id());
+
+    let blockhash = client.get_latest_blockhash()?;
+    let tx = Transaction::new_signed_with_payer(
+        &[return_data_instr],
+        Some(&config.keypair().pubkey()),
+        &[config.keypair()],
+        blockhash,
+    );
+
+    let return_data = util::send_and_confirm(config, client, &tx)?;
+
+    let deepest = |transaction: &TransactionReport| {
+        transaction
+            .invocations
+            .iter()
+            .flat_map(|invocation| invocation.iter())
+            .filter(|invocation| invocation.succeeded())
+            .map(|invocation| invocation.depth)
+            .max()
+    };
+    let max_depth_reached = deepest(&max_depth);
+
+    let returned: Option<RecursiveReturnData> = match max_depth.invocations.first() {
+        Some(invocation) => invocation.decode_return_data()?,
+        None => None,
+    };
+    let depths = match returned {
+        Some(returned) => returned.depths,
+        None => bail!("expected return data"),
+    };
+    let expected_depths: Vec<u8> = (1..=MAX_INVOKE_DEPTH).rev().collect();
+    if depths != expected_depths {
+        bail!(
+            "expected return data from depths {:?}, got {:?}",
+            expected_depths,
+            depths
+        );
+    }
+
+    Ok(InvokeDepthReport {
+        transactions: vec![
+            max_depth.with_label("max depth"),
+            past_max_depth.with_label("past max depth"),
+            return_data.with_label("return data"),
+        ],
+        max_depth_reached,
+        depths,
+        depth_error,
+    })
+}
+
+#[derive(Serialize)]
+pub struct InvokeDepthReport {
+    pub transactions: Vec<TransactionReport>,
+    /// The deepest invocation that succeeded.
+    pub max_depth_reached: Option<usize>,
+    /// The depths in the returned `RecursiveReturnData`, deepest first.
+    pub depths: Vec<u8>,
+    /// Why invoking past the max depth failed.
+    pub depth_error: Option<String>,
+}
+
+impl Report for InvokeDepthReport {
+    fn print_text(&self) {
+        for transaction in &self.transactions {
+            transaction.print_text();
+        }
+
+        if let Some(depth) = self.max_depth_reached {
+            println!("deepest successful invocation: {}", depth);
+        }
+        println!("returned depths: {:?}", self.depths);
+        if let Some(error) = &self.depth_error {
+            println!("invoking past the max depth: {}", error);
+        }
+    }
 }
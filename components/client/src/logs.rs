@@ -0,0 +1,590 @@
+//! Parsing of transaction log messages into a tree of program invocations.
+//!
+//! The runtime brackets each program's logs with lines like
+//!
+//! ```text
+//! Program <id> invoke [1]
+//! Program log: <msg! output>
+//! Program <id> consumed 1234 of 200000 compute units
+//! Program <id> success
+//! ```
+//!
+//! where the number in brackets is the invocation depth,
+//! 1 for instructions in the transaction and greater for CPI.
+
+use anyhow::Result;
+use common::ReturnData;
+use serde::Serialize;
+use solana_sdk::{instruction::CompiledInstruction, pubkey::Pubkey};
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Invocation {
+    pub program_id: String,
+    pub depth: usize,
+    /// Index of the instruction in the transaction, for depth 1 invocations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instruction_index: Option<usize>,
+    /// `Program log:` messages without their prefix,
+    /// and any other lines logged while this program was running.
+    pub logs: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub units_consumed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub units_budget: Option<u64>,
+    /// The base64 data from a `Program return:` line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_data: Option<String>,
+    /// `success` or the failure message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    pub invocations: Vec<Invocation>,
+}
+
+impl Invocation {
+    pub fn succeeded(&self) -> bool {
+        self.result.as_deref() == Some("success")
+    }
+
+    /// This invocation and all of its descendants, depth first.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = &Invocation> + '_> {
+        Box::new(std::iter::once(self).chain(self.invocations.iter().flat_map(|i| i.iter())))
+    }
+
+    /// Decode the return data as the type the instruction documents.
+    ///
+    /// The runtime logs return data when each program finishes,
+    /// so a caller that doesn't set return data reports what its callee set.
+    pub fn decode_return_data<T: ReturnData>(&self) -> Result<Option<T>> {
+        let return_data = match &self.return_data {
+            Some(return_data) => return_data,
+            None => return Ok(None),
+        };
+        let data = base64::decode(return_data)?;
+
+        Ok(Some(T::decode_return_data(&data)?))
+    }
+}
+
+/// Parse log messages into the top-level invocations.
+///
+/// Logs may be truncated by the runtime,
+/// in which case unfinished invocations are returned without a result.
+pub fn parse_logs(logs: &[String]) -> Vec<Invocation> {
+    let mut roots = vec![];
+    let mut stack: Vec<Invocation> = vec![];
+
+    for line in logs {
+        if let Some((program_id, depth)) = parse_invoke(line) {
+            stack.push(Invocation {
+                program_id: program_id.to_string(),
+                depth,
+                ..Invocation::default()
+            });
+        } else if let Some(current) = stack.last_mut() {
+            let program_prefix = format!("Program {} ", current.program_id);
+            if let Some(msg) = line.strip_prefix("Program log: ") {
+                current.logs.push(msg.to_string());
+            } else if let Some(rest) = line.strip_prefix("Program return: ") {
+                current.return_data = rest.split_whitespace().nth(1).map(str::to_string);
+            } else if let Some(rest) = line.strip_prefix(&program_prefix) {
+                if let Some((consumed, budget)) = parse_consumed(rest) {
+                    current.units_consumed = Some(consumed);
+                    current.units_budget = Some(budget);
+                } else if rest == "success" || rest.starts_with("failed: ") {
+                    current.result = Some(rest.to_string());
+                    let finished = stack.pop().expect("current");
+                    match stack.last_mut() {
+                        Some(parent) => parent.invocations.push(finished),
+                        None => roots.push(finished),
+                    }
+                } else {
+                    current.logs.push(line.to_string());
+                }
+            } else {
+                current.logs.push(line.to_string());
+            }
+        }
+    }
+
+    // Unwind anything left open by truncated logs.
+    while let Some(unfinished) = stack.pop() {
+        match stack.last_mut() {
+            Some(parent) => parent.invocations.push(unfinished),
+            None => roots.push(unfinished),
+        }
+    }
+
+    roots
+}
+
+/// Match top-level invocations to the instructions that caused them.
+///
+/// Precompiles like secp256k1 and ed25519 don't log anything,
+/// so instructions and invocations don't line up one-to-one.
+pub fn assign_instruction_indexes(
+    invocations: &mut [Invocation],
+    account_keys: &[Pubkey],
+    instructions: &[CompiledInstruction],
+) {
+    let mut invocations = invocations.iter_mut().peekable();
+    for (instruction_index, instruction) in instructions.iter().enumerate() {
+        let program_id = instruction.program_id(account_keys).to_string();
+        if let Some(invocation) = invocations.next_if(|i| i.program_id == program_id) {
+            invocation.instruction_index = Some(instruction_index);
+        }
+    }
+}
+
+/// A `checkpoint: <label>` message from the program,
+/// and the compute units remaining at that point.
+#[derive(Serialize, Debug, Clone)]
+pub struct Checkpoint {
+    pub label: String,
+    pub remaining: u64,
+}
+
+/// The compute units spent between a checkpoint and the one before it.
+#[derive(Serialize, Debug, Clone)]
+pub struct CheckpointCost {
+    pub label: String,
+    pub units: u64,
+}
+
+/// Find the checkpoints logged by an invocation, in order.
+///
+/// Each is a `checkpoint: <label>` log followed by a
+/// `Program consumption: <n> units remaining` log.
+pub fn checkpoints(invocation: &Invocation) -> Vec<Checkpoint> {
+    let mut checkpoints = vec![];
+    let mut label = None;
+
+    for log in &invocation.logs {
+        if let Some(l) = log.strip_prefix("checkpoint: ") {
+            label = Some(l.to_string());
+        } else if let Some(remaining) = parse_remaining(log) {
+            if let Some(label) = label.take() {
+                checkpoints.push(Checkpoint { label, remaining });
+            }
+        }
+    }
+
+    checkpoints
+}
+
+/// The cost of each checkpoint, less the cost of logging a checkpoint,
+/// which the program measures as the `overhead` checkpoint.
+pub fn checkpoint_costs(checkpoints: &[Checkpoint]) -> Vec<CheckpointCost> {
+    let costs: Vec<_> = checkpoints
+        .windows(2)
+        .map(|pair| CheckpointCost {
+            label: pair[1].label.clone(),
+            units: pair[0].remaining.saturating_sub(pair[1].remaining),
+        })
+        .collect();
+
+    let overhead = costs
+        .iter()
+        .find(|cost| cost.label == "overhead")
+        .map(|cost| cost.units)
+        .unwrap_or(0);
+
+    costs
+        .into_iter()
+        .filter(|cost| cost.label != "overhead")
+        .map(|cost| CheckpointCost {
+            units: cost.units.saturating_sub(overhead),
+            ..cost
+        })
+        .collect()
+}
+
+/// Print invocations as an indented tree.
+pub fn print_invocations(invocations: &[Invocation], indent: usize) {
+    for invocation in invocations {
+        let pad = " ".repeat(indent + (invocation.depth.saturating_sub(1)) * 4);
+        let instruction = invocation
+            .instruction_index
+            .map(|idx| format!("instruction {}: ", idx))
+            .unwrap_or_default();
+        let units = match (invocation.units_consumed, invocation.units_budget) {
+            (Some(consumed), Some(budget)) => format!(" ({} of {} CU)", consumed, budget),
+            _ => String::new(),
+        };
+        let result = invocation.result.as_deref().unwrap_or("unfinished");
+        println!(
+            "{}{}[{}] {}{} {}",
+            pad, instruction, invocation.depth, invocation.program_id, units, result
+        );
+
+        for log in &invocation.logs {
+            for line in log.lines() {
+                println!("{}    {}", pad, line);
+            }
+        }
+        if let Some(return_data) = &invocation.return_data {
+            println!("{}    return: {}", pad, return_data);
+        }
+
+        print_invocations(&invocation.invocations, indent);
+    }
+}
+
+fn parse_invoke(line: &str) -> Option<(&str, usize)> {
+    let rest = line.strip_prefix("Program ")?;
+    let (program_id, rest) = rest.split_once(" invoke [")?;
+    let depth = rest.strip_suffix(']')?.parse().ok()?;
+    Some((program_id, depth))
+}
+
+/// Parse `Program consumption: 1234 units remaining`.
+fn parse_remaining(line: &str) -> Option<u64> {
+    let rest = line.strip_prefix("Program consumption: ")?;
+    rest.strip_suffix(" units remaining")?.parse().ok()
+}
+
+/// Parse `consumed 1234 of 200000 compute units`.
+fn parse_consumed(rest: &str) -> Option<(u64, u64)> {
+    let rest = rest.strip_prefix("consumed ")?;
+    let rest = rest.strip_suffix(" compute units")?;
+    let (consumed, budget) = rest.split_once(" of ")?;
+    Some((consumed.parse().ok()?, budget.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = "AYn3tq1XGucC9EtEnmAHgogPRCUJdB4arFRXszqxxubu";
+    const SYSTEM: &str = "11111111111111111111111111111111";
+
+    fn lines(logs: &[&str]) -> Vec<String> {
+        logs.iter()
+            .map(|line| {
+                line.replace("{program}", PROGRAM)
+                    .replace("{system}", SYSTEM)
+            })
+            .collect()
+    }
+
+    /// One line per invocation, depth first, indented by depth:
+    /// `<program> <result> <units consumed> <logs>`.
+    fn describe(invocations: &[Invocation]) -> Vec<String> {
+        invocations
+            .iter()
+            .flat_map(Invocation::iter)
+            .map(|invocation| {
+                let program = match invocation.program_id.as_str() {
+                    PROGRAM => "program",
+                    SYSTEM => "system",
+                    other => other,
+                };
+                let units = invocation
+                    .units_consumed
+                    .map(|units| units.to_string())
+                    .unwrap_or_else(|| "-".to_string());
+                format!(
+                    "{}{} {} {} {:?}",
+                    "  ".repeat(invocation.depth - 1),
+                    program,
+                    invocation.result.as_deref().unwrap_or("unfinished"),
+                    units,
+                    invocation.logs
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_logs_table() {
+        let cases: &[(&str, &[&str], &[&str])] = &[
+            ("empty", &[], &[]),
+            (
+                "single instruction",
+                &[
+                    "Program {program} invoke [1]",
+                    "Program log: print sysvars",
+                    "Program {program} consumed 1234 of 200000 compute units",
+                    "Program {program} success",
+                ],
+                &[r#"program success 1234 ["print sysvars"]"#],
+            ),
+            (
+                "two instructions",
+                &[
+                    "Program {program} invoke [1]",
+                    "Program {program} consumed 100 of 200000 compute units",
+                    "Program {program} success",
+                    "Program {program} invoke [1]",
+                    "Program {program} consumed 200 of 199900 compute units",
+                    "Program {program} success",
+                ],
+                &["program success 100 []", "program success 200 []"],
+            ),
+            (
+                "nested invokes",
+                &[
+                    "Program {program} invoke [1]",
+                    "Program log: invoke caller",
+                    "Program {program} invoke [2]",
+                    "Program log: invoke callee",
+                    "Program {program} consumed 500 of 190000 compute units",
+                    "Program {program} success",
+                    "Program {system} invoke [2]",
+                    "Program {system} success",
+                    "Program log: done",
+                    "Program {program} consumed 12000 of 200000 compute units",
+                    "Program {program} success",
+                ],
+                &[
+                    r#"program success 12000 ["invoke caller", "done"]"#,
+                    r#"  program success 500 ["invoke callee"]"#,
+                    "  system success - []",
+                ],
+            ),
+            (
+                "failed invocation",
+                &[
+                    "Program {program} invoke [1]",
+                    "Program log: demo cpi guard",
+                    "Program {program} invoke [2]",
+                    "Program log: refusing to run via CPI",
+                    "Program {program} consumed 300 of 190000 compute units",
+                    "Program {program} failed: invalid instruction data",
+                    "Program {program} consumed 10000 of 200000 compute units",
+                    "Program {program} failed: invalid instruction data",
+                ],
+                &[
+                    r#"program failed: invalid instruction data 10000 ["demo cpi guard"]"#,
+                    r#"  program failed: invalid instruction data 300 ["refusing to run via CPI"]"#,
+                ],
+            ),
+            (
+                "exceeded compute budget",
+                &[
+                    "Program {program} invoke [1]",
+                    "Program log: stake_history: AccountInfo {",
+                    "Program {program} consumed 200000 of 200000 compute units",
+                    "Program failed to complete: exceeded maximum number of instructions allowed (200000) at instruction #1400",
+                    "Program {program} failed: Program failed to complete",
+                ],
+                &[
+                    r#"program failed: Program failed to complete 200000 ["stake_history: AccountInfo {", "Program failed to complete: exceeded maximum number of instructions allowed (200000) at instruction #1400"]"#,
+                ],
+            ),
+            (
+                "missing consumed lines",
+                &[
+                    "Program {system} invoke [1]",
+                    "Program {system} success",
+                    "Program {program} invoke [1]",
+                    "Program {program} success",
+                ],
+                &["system success - []", "program success - []"],
+            ),
+            (
+                "truncated",
+                &[
+                    "Program {program} invoke [1]",
+                    "Program log: invoke caller",
+                    "Program {program} invoke [2]",
+                    "Program log: invoke callee",
+                    "Log truncated",
+                ],
+                &[
+                    r#"program unfinished - ["invoke caller"]"#,
+                    r#"  program unfinished - ["invoke callee", "Log truncated"]"#,
+                ],
+            ),
+            (
+                "lines before any invocation",
+                &[
+                    "Log truncated",
+                    "Program {program} invoke [1]",
+                    "Program {program} success",
+                ],
+                &["program success - []"],
+            ),
+        ];
+
+        for (name, logs, expected) in cases {
+            let invocations = parse_logs(&lines(logs));
+            assert_eq!(describe(&invocations), *expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn parse_logs_return_data() {
+        let invocations = parse_logs(&lines(&[
+            "Program {program} invoke [1]",
+            "Program {program} invoke [2]",
+            "Program return: {program} AgAAAAEC",
+            "Program {program} success",
+            "Program return: {program} AwAAAAMCAQ==",
+            "Program {program} success",
+        ]));
+
+        assert_eq!(invocations.len(), 1);
+        assert_eq!(invocations[0].return_data.as_deref(), Some("AwAAAAMCAQ=="));
+        assert_eq!(
+            invocations[0].invocations[0].return_data.as_deref(),
+            Some("AgAAAAEC")
+        );
+        assert!(invocations[0].succeeded());
+    }
+
+    fn invocation(logs: &[&str]) -> Invocation {
+        Invocation {
+            program_id: PROGRAM.to_string(),
+            depth: 1,
+            logs: logs.iter().map(|log| log.to_string()).collect(),
+            ..Invocation::default()
+        }
+    }
+
+    fn labelled<T>(items: &[T], f: impl Fn(&T) -> (&str, u64)) -> Vec<(String, u64)> {
+        items
+            .iter()
+            .map(|item| {
+                let (label, units) = f(item);
+                (label.to_string(), units)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn checkpoints_table() {
+        let cases: &[(&str, &[&str], &[(&str, u64)])] = &[
+            ("none", &["create 3 state accounts"], &[]),
+            (
+                "each followed by the remaining units",
+                &[
+                    "checkpoint: start",
+                    "Program consumption: 199000 units remaining",
+                    "checkpoint: overhead",
+                    "Program consumption: 198900 units remaining",
+                    "checkpoint: create",
+                    "Program consumption: 190000 units remaining",
+                ],
+                &[("start", 199000), ("overhead", 198900), ("create", 190000)],
+            ),
+            (
+                "other logs in between",
+                &[
+                    "checkpoint: clock",
+                    "clock: Clock { slot: 44745 }",
+                    "Program consumption: 150000 units remaining",
+                ],
+                &[("clock", 150000)],
+            ),
+            (
+                "label without remaining units",
+                &[
+                    "checkpoint: start",
+                    "Program consumption: 199000 units remaining",
+                    "checkpoint: create",
+                ],
+                &[("start", 199000)],
+            ),
+            (
+                "remaining units without label",
+                &[
+                    "Program consumption: 199500 units remaining",
+                    "checkpoint: start",
+                    "Program consumption: 199000 units remaining",
+                ],
+                &[("start", 199000)],
+            ),
+            (
+                "malformed remaining units",
+                &[
+                    "checkpoint: start",
+                    "Program consumption: many units remaining",
+                ],
+                &[],
+            ),
+        ];
+
+        for (name, logs, expected) in cases {
+            let found = checkpoints(&invocation(logs));
+            assert_eq!(
+                labelled(&found, |c| (c.label.as_str(), c.remaining)),
+                labelled(expected, |(label, units)| (*label, *units)),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn checkpoint_costs_table() {
+        let cases: &[(&str, &[(&str, u64)], &[(&str, u64)])] = &[
+            ("none", &[], &[]),
+            ("only start", &[("start", 1000)], &[]),
+            (
+                "overhead subtracted",
+                &[
+                    ("start", 1000),
+                    ("overhead", 900),
+                    ("create", 500),
+                    ("create", 150),
+                ],
+                &[("create", 300), ("create", 250)],
+            ),
+            (
+                "no overhead",
+                &[("start", 1000), ("clock", 800), ("rent", 750)],
+                &[("clock", 200), ("rent", 50)],
+            ),
+            (
+                "cheaper than the overhead",
+                &[("start", 1000), ("overhead", 900), ("rent", 850)],
+                &[("rent", 0)],
+            ),
+        ];
+
+        for (name, checkpoints, expected) in cases {
+            let checkpoints: Vec<_> = checkpoints
+                .iter()
+                .map(|(label, remaining)| Checkpoint {
+                    label: label.to_string(),
+                    remaining: *remaining,
+                })
+                .collect();
+            let costs = checkpoint_costs(&checkpoints);
+            assert_eq!(
+                labelled(&costs, |c| (c.label.as_str(), c.units)),
+                labelled(expected, |(label, units)| (*label, *units)),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn checkpoints_from_parsed_logs() {
+        let invocations = parse_logs(&lines(&[
+            "Program {program} invoke [1]",
+            "Program log: create 2 state accounts",
+            "Program log: checkpoint: start",
+            "Program consumption: 199000 units remaining",
+            "Program log: checkpoint: overhead",
+            "Program consumption: 198800 units remaining",
+            "Program {system} invoke [2]",
+            "Program {system} success",
+            "Program log: checkpoint: create",
+            "Program consumption: 190000 units remaining",
+            "Program {system} invoke [2]",
+            "Program {system} success",
+            "Program log: checkpoint: create",
+            "Program consumption: 181000 units remaining",
+            "Program {program} consumed 19000 of 200000 compute units",
+            "Program {program} success",
+        ]));
+
+        let costs = checkpoint_costs(&checkpoints(&invocations[0]));
+        assert_eq!(
+            labelled(&costs, |c| (c.label.as_str(), c.units)),
+            vec![("create".to_string(), 8600), ("create".to_string(), 8800)]
+        );
+    }
+}
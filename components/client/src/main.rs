@@ -1,38 +1,112 @@
 use anyhow::Result;
 use clap::Parser;
 use log::info;
-use solana_sdk::signature::Signer;
+use util::Requirements;
 
+mod bench;
+mod caller;
+mod cleanup;
 mod ed25519;
+mod introspect;
 mod invoke;
+mod logs;
+mod nonce;
+mod output;
+mod policies;
 mod pubsub_client;
 mod pubsub_client_async;
 mod secp256k1;
+mod state;
 mod sysprog;
+mod sysvar_access;
 mod sysvars;
 mod util;
+mod watch_sysvars;
 
 #[derive(Parser)]
 struct Opts {
     #[clap(subcommand)]
     command: Command,
-    #[clap(long, global = true)]
-    url: Option<String>,
+    #[clap(flatten)]
+    config: util::ConfigOpts,
 }
 
 #[derive(clap::Subcommand)]
 enum Command {
-    PrintSysvarsViaProgram,
+    PrintSysvarsViaProgram(sysvars::PrintSysvarsOpts),
     PrintSysvarsViaClient,
+    WatchSysvars(watch_sysvars::WatchSysvarsOpts),
+    CompareSysvarAccess,
     DemoSecp256k1VerifyBasic,
     DemoSecp256k1CustomMany,
     DemoSecp256k1Recover,
     DemoEd25519,
     DemoInvoke,
+    DemoCpiGuard,
+    DemoInvokeDepth,
+    DemoIntrospectInstructions,
+    DemoTransactionPolicies,
     DemoPubsubClient,
     DemoPubsubClientAsync,
     DemoSystemProgramCreateAccountCpi,
     DemoSystemProgramTransferAllocAssignCpi,
+    DemoSystemProgramCreateAccountWithSeedCpi,
+    DemoSystemProgramAllocateWithSeedCpi,
+    DemoSystemProgramAssignWithSeedCpi,
+    DemoSystemProgramTransferWithSeedCpi,
+    DemoSystemProgramNonceCpi,
+    DemoSystemProgramPrefundedCpi,
+    DemoCallerProgram(caller::CallerProgramOpts),
+    DemoAccountState,
+    DemoCreateStateAccounts(state::CreateStateAccountsOpts),
+    ShowAccount(state::ShowAccountOpts),
+    ResizeAccount(state::ResizeAccountOpts),
+    Cleanup,
+    CreateNonceAccount(nonce::CreateNonceAccountOpts),
+    SignNonceTransaction(nonce::SignNonceTransactionOpts),
+    SubmitTransaction(nonce::SubmitTransactionOpts),
+    Bench(bench::BenchOpts),
+}
+
+impl Command {
+    fn requirements(&self) -> Requirements {
+        match self {
+            Command::PrintSysvarsViaClient | Command::WatchSysvars(_) => Requirements::READ_ONLY,
+            Command::DemoPubsubClient | Command::DemoPubsubClientAsync => Requirements::WALLET,
+            Command::SignNonceTransaction(opts) if opts.nonce_blockhash.is_some() => {
+                Requirements::OFFLINE_WALLET
+            }
+            Command::SignNonceTransaction(_) => Requirements::WALLET,
+            Command::SubmitTransaction(_) => Requirements::READ_ONLY,
+            Command::ShowAccount(_) => Requirements::PROGRAM,
+            Command::PrintSysvarsViaProgram(_)
+            | Command::CompareSysvarAccess
+            | Command::DemoSecp256k1VerifyBasic
+            | Command::DemoSecp256k1CustomMany
+            | Command::DemoSecp256k1Recover
+            | Command::DemoEd25519
+            | Command::DemoInvoke
+            | Command::DemoCpiGuard
+            | Command::DemoInvokeDepth
+            | Command::DemoIntrospectInstructions
+            | Command::DemoTransactionPolicies
+            | Command::DemoSystemProgramCreateAccountCpi
+            | Command::DemoSystemProgramTransferAllocAssignCpi
+            | Command::DemoSystemProgramCreateAccountWithSeedCpi
+            | Command::DemoSystemProgramAllocateWithSeedCpi
+            | Command::DemoSystemProgramAssignWithSeedCpi
+            | Command::DemoSystemProgramTransferWithSeedCpi
+            | Command::DemoSystemProgramNonceCpi
+            | Command::DemoSystemProgramPrefundedCpi
+            | Command::DemoCallerProgram(_)
+            | Command::DemoAccountState
+            | Command::DemoCreateStateAccounts(_)
+            | Command::ResizeAccount(_)
+            | Command::Cleanup
+            | Command::CreateNonceAccount(_)
+            | Command::Bench(_) => Requirements::WALLET_AND_PROGRAM,
+        }
+    }
 }
 
 fn _main() -> Result<()> {
@@ -47,53 +121,176 @@ fn main() -> Result<()> {
         .init();
 
     let opts = Opts::parse();
+    let output = opts.config.output;
+
+    let result = run(opts);
+
+    if let Err(e) = &result {
+        output::print_error(output, e)?;
+    }
+
+    result
+}
+
+fn run(opts: Opts) -> Result<()> {
     let command = opts.command;
 
-    let config = util::load_config(opts.url)?;
-    let client = util::connect(&config)?;
-    let version = client.get_version()?;
-    info!("version: {}", version);
+    let requirements = command.requirements();
+
+    let mut config = util::load_config(opts.config)?;
+    let client = if requirements.rpc {
+        let client = util::connect(&config)?;
+        let version = client.get_version()?;
+        info!("version: {}", version);
+        client
+    } else {
+        info!("offline, not connecting to {}", config.json_rpc_url);
+        util::offline_client(&config)
+    };
 
-    let program_keypair = util::get_program_keypair(&client)?;
-    println!("program id: {:#?}", program_keypair.pubkey());
+    util::load_requirements(&mut config, &client, requirements)?;
+    if requirements.program {
+        info!("program id: {}", config.program_id());
+    }
+
+    let output = config.output;
 
     match command {
-        Command::PrintSysvarsViaProgram => {
-            sysvars::print_sysvars_via_program(&config, &client, &program_keypair)?;
+        Command::PrintSysvarsViaProgram(opts) => {
+            let report = sysvars::print_sysvars_via_program(&config, &client, opts)?;
+            output::print_report(output, &report)?;
         }
         Command::PrintSysvarsViaClient => {
-            sysvars::print_sysvars_via_client(&client)?;
+            let report = sysvars::print_sysvars_via_client(&client)?;
+            output::print_report(output, &report)?;
+        }
+        Command::WatchSysvars(opts) => {
+            watch_sysvars::watch_sysvars(&config, &client, opts)?;
+        }
+        Command::CompareSysvarAccess => {
+            let report = sysvar_access::compare_sysvar_access(&config, &client)?;
+            output::print_report(output, &report)?;
         }
         Command::DemoSecp256k1VerifyBasic => {
-            secp256k1::demo_secp256k1_verify_basic(&config, &client, &program_keypair)?;
+            let report = secp256k1::demo_secp256k1_verify_basic(&config, &client)?;
+            output::print_report(output, &report)?;
         }
         Command::DemoSecp256k1CustomMany => {
-            secp256k1::demo_secp256k1_custom_many(&config, &client, &program_keypair)?;
+            let report = secp256k1::demo_secp256k1_custom_many(&config, &client)?;
+            output::print_report(output, &report)?;
         }
         Command::DemoSecp256k1Recover => {
-            secp256k1::demo_secp256k1_recover(&config, &client, &program_keypair)?;
+            let report = secp256k1::demo_secp256k1_recover(&config, &client)?;
+            output::print_report(output, &report)?;
         }
         Command::DemoEd25519 => {
-            ed25519::demo_ed25519_instruction(&config, &client, &program_keypair)?;
+            let report = ed25519::demo_ed25519_instruction(&config, &client)?;
+            output::print_report(output, &report)?;
         }
         Command::DemoInvoke => {
-            invoke::demo_invoke(&config, &client, &program_keypair)?;
+            let report = invoke::demo_invoke(&config, &client)?;
+            output::print_report(output, &report)?;
+        }
+        Command::DemoCpiGuard => {
+            let report = invoke::demo_cpi_guard(&config, &client)?;
+            output::print_report(output, &report)?;
+        }
+        Command::DemoInvokeDepth => {
+            let report = invoke::demo_invoke_depth(&config, &client)?;
+            output::print_report(output, &report)?;
+        }
+        Command::DemoIntrospectInstructions => {
+            let report = introspect::demo_introspect_instructions(&config, &client)?;
+            output::print_report(output, &report)?;
+        }
+        Command::DemoTransactionPolicies => {
+            let report = policies::demo_transaction_policies(&config, &client)?;
+            output::print_report(output, &report)?;
         }
         Command::DemoPubsubClient => {
-            pubsub_client::demo_pubsub_client(&config, &client, &program_keypair)?;
+            pubsub_client::demo_pubsub_client(&config, &client)?;
         }
         Command::DemoPubsubClientAsync => {
             pubsub_client_async::demo_pubsub_client_async(&config, client)?;
         }
         Command::DemoSystemProgramCreateAccountCpi => {
-            sysprog::demo_system_program_create_account_cpi(&config, &client, &program_keypair)?;
+            let report = sysprog::demo_system_program_create_account_cpi(&config, &client)?;
+            output::print_report(output, &report)?;
         }
         Command::DemoSystemProgramTransferAllocAssignCpi => {
-            sysprog::demo_system_program_transfer_alloc_assign_cpi(
-                &config,
-                &client,
-                &program_keypair,
-            )?;
+            let report = sysprog::demo_system_program_transfer_alloc_assign_cpi(&config, &client)?;
+            output::print_report(output, &report)?;
+        }
+        Command::DemoSystemProgramCreateAccountWithSeedCpi => {
+            let report =
+                sysprog::demo_system_program_create_account_with_seed_cpi(&config, &client)?;
+            output::print_report(output, &report)?;
+        }
+        Command::DemoSystemProgramAllocateWithSeedCpi => {
+            let report = sysprog::demo_system_program_allocate_with_seed_cpi(&config, &client)?;
+            output::print_report(output, &report)?;
+        }
+        Command::DemoSystemProgramAssignWithSeedCpi => {
+            let report = sysprog::demo_system_program_assign_with_seed_cpi(&config, &client)?;
+            output::print_report(output, &report)?;
+        }
+        Command::DemoSystemProgramTransferWithSeedCpi => {
+            let report = sysprog::demo_system_program_transfer_with_seed_cpi(&config, &client)?;
+            output::print_report(output, &report)?;
+        }
+        Command::DemoSystemProgramNonceCpi => {
+            let report = sysprog::demo_system_program_nonce_cpi(&config, &client)?;
+            output::print_report(output, &report)?;
+        }
+        Command::DemoSystemProgramPrefundedCpi => {
+            let report = sysprog::demo_system_program_prefunded_cpi(&config, &client)?;
+            output::print_report(output, &report)?;
+        }
+        Command::DemoCallerProgram(opts) => {
+            let report = caller::demo_caller_program(&config, &client, &opts)?;
+            output::print_report(output, &report)?;
+        }
+        Command::DemoAccountState => {
+            let report = state::demo_account_state(&config, &client)?;
+            output::print_report(output, &report)?;
+        }
+        Command::DemoCreateStateAccounts(opts) => {
+            let report = state::demo_create_state_accounts(&config, &client, &opts)?;
+            output::print_report(output, &report)?;
+        }
+        Command::ShowAccount(opts) => {
+            let report = state::show_account(&config, &client, &opts)?;
+            output::print_report(output, &report)?;
+        }
+        Command::ResizeAccount(opts) => {
+            let report = state::resize_account(&config, &client, &opts)?;
+            output::print_report(output, &report)?;
+        }
+        Command::Cleanup => {
+            let report = cleanup::cleanup(&config, &client)?;
+            output::print_report(output, &report)?;
+        }
+        Command::CreateNonceAccount(opts) => {
+            let report = nonce::create_nonce_account(&config, &client, &opts)?;
+            output::print_report(output, &report)?;
+        }
+        Command::SignNonceTransaction(opts) => {
+            let report = nonce::sign_nonce_transaction(&config, &client, &opts)?;
+            output::print_report(output, &report)?;
+        }
+        Command::SubmitTransaction(opts) => {
+            let report = nonce::submit_transaction(&config, &client, &opts)?;
+            output::print_report(output, &report)?;
+        }
+        Command::Bench(opts) => {
+            let report = bench::bench(&config, &client, opts)?;
+            output::print_report(output, &report)?;
+            if !report.regressions.is_empty() {
+                anyhow::bail!(
+                    "{} operations exceeded the baseline",
+                    report.regressions.len()
+                );
+            }
         }
     }
 
@@ -0,0 +1,314 @@
+//! Durable nonce transactions, signed on one machine and submitted from another.
+//!
+//! A transaction normally expires about two minutes after its blockhash.
+//! A transaction whose blockhash is the one stored in a nonce account,
+//! and whose first instruction advances that nonce account,
+//! stays valid until the nonce is advanced.
+//!
+//! The workflow is:
+//!
+//! - `create-nonce-account`, online, once.
+//! - `sign-nonce-transaction --nonce-blockhash <hash>`, on the offline machine,
+//!   with the blockhash printed by `create-nonce-account`,
+//!   or by the previous `submit-transaction`.
+//! - `submit-transaction`, online, with the file written by the offline machine.
+
+use crate::output::{self, Report, TransactionReport};
+use crate::util::{self, Config};
+use anyhow::{bail, Context, Result};
+use common::{DemoSystemProgramNonceInstruction, NonceAction};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use solana_client::{nonce_utils, rpc_client::RpcClient};
+use solana_sdk::{
+    hash::Hash, nonce, pubkey::Pubkey, signature::Signer, system_instruction,
+    transaction::Transaction,
+};
+use std::fs::File;
+
+#[derive(clap::Args)]
+pub struct CreateNonceAccountOpts {
+    /// The authority that will sign durable transactions, defaults to the wallet.
+    #[clap(long)]
+    pub authority: Option<Pubkey>,
+}
+
+#[derive(clap::Args)]
+pub struct SignNonceTransactionOpts {
+    #[clap(long)]
+    pub nonce_account: Pubkey,
+    /// The blockhash stored in the nonce account.
+    ///
+    /// With this the command does not connect to the network.
+    /// Without it the blockhash is fetched from the nonce account.
+    #[clap(long)]
+    pub nonce_blockhash: Option<Hash>,
+    /// The recipient of the transfer.
+    #[clap(long)]
+    pub to: Pubkey,
+    #[clap(long)]
+    pub lamports: u64,
+    /// Where to write the signed transaction.
+    #[clap(long)]
+    pub out: String,
+}
+
+#[derive(clap::Args)]
+pub struct SubmitTransactionOpts {
+    /// A file written by `sign-nonce-transaction`.
+    #[clap(long)]
+    pub file: String,
+}
+
+/// The file written by `sign-nonce-transaction` and read by `submit-transaction`.
+#[derive(Serialize, Deserialize)]
+struct SignedTransactionFile {
+    nonce_account: String,
+    /// The bincode-serialized transaction, base64 encoded.
+    transaction: String,
+}
+
+/// Create a nonce account via the program, then hand its authority to a signer.
+///
+/// The program creates the account with a PDA as its authority,
+/// which can advance the nonce via CPI.
+/// But the runtime only accepts a durable transaction
+/// if its first instruction is a top-level advance signed by the authority,
+/// which a PDA can't sign, so both instructions run in one transaction
+/// and the nonce account ends up owned by a keypair.
+pub fn create_nonce_account(
+    config: &Config,
+    client: &RpcClient,
+    opts: &CreateNonceAccountOpts,
+) -> Result<NonceAccountReport> {
+    let payer = config.keypair().pubkey();
+    let program_id = config.program_id();
+    let authority = opts.authority.unwrap_or(payer);
+
+    let nonce_account_seed: [u8; 16] = rand::thread_rng().gen();
+    let (nonce_account_pda, nonce_account_bump_seed) =
+        Pubkey::find_program_address(&[payer.as_ref(), &nonce_account_seed], program_id);
+    let (nonce_authority_pda, nonce_authority_bump_seed) =
+        Pubkey::find_program_address(&[nonce_account_pda.as_ref()], program_id);
+
+    let lamports = client.get_minimum_balance_for_rent_exemption(nonce::State::size())?;
+
+    let instrs: Vec<_> = [
+        NonceAction::Create { lamports },
+        NonceAction::Authorize {
+            new_authority: authority,
+        },
+    ]
+    .into_iter()
+    .map(|action| {
+        DemoSystemProgramNonceInstruction {
+            payer,
+            nonce_account_pda,
+            nonce_authority_pda,
+            nonce_account_seed,
+            nonce_account_bump_seed,
+            nonce_authority_bump_seed,
+            action,
+        }
+        .build_instruction(program_id)
+    })
+    .collect();
+
+    let blockhash = client.get_latest_blockhash()?;
+
+    let tx =
+        Transaction::new_signed_with_payer(&instrs, Some(&payer), &[config.keypair()], blockhash);
+
+    let transaction = util::send_and_confirm(config, client, &tx)?;
+
+    let (authority, nonce_blockhash) = if config.simulate.is_some() {
+        (authority, None)
+    } else {
+        let data = get_nonce_data(config, client, &nonce_account_pda)?;
+        (data.authority, Some(data.blockhash.to_string()))
+    };
+
+    Ok(NonceAccountReport {
+        nonce_account: nonce_account_pda,
+        authority,
+        nonce_blockhash,
+        transaction,
+    })
+}
+
+/// Sign a transfer that uses the nonce account's blockhash, and write it to a file.
+///
+/// The wallet pays the fee and must be the nonce authority.
+pub fn sign_nonce_transaction(
+    config: &Config,
+    client: &RpcClient,
+    opts: &SignNonceTransactionOpts,
+) -> Result<SignedTransactionReport> {
+    let payer = config.keypair().pubkey();
+
+    let nonce_blockhash = match opts.nonce_blockhash {
+        Some(nonce_blockhash) => nonce_blockhash,
+        None => {
+            let data = get_nonce_data(config, client, &opts.nonce_account)?;
+            if data.authority != payer {
+                bail!(
+                    "nonce authority is {}, but the wallet is {}",
+                    data.authority,
+                    payer
+                );
+            }
+            data.blockhash
+        }
+    };
+
+    let instrs = [
+        // Must be first for the runtime to treat this as a durable transaction.
+        system_instruction::advance_nonce_account(&opts.nonce_account, &payer),
+        system_instruction::transfer(&payer, &opts.to, opts.lamports),
+    ];
+
+    let tx = Transaction::new_signed_with_payer(
+        &instrs,
+        Some(&payer),
+        &[config.keypair()],
+        nonce_blockhash,
+    );
+
+    let file = SignedTransactionFile {
+        nonce_account: opts.nonce_account.to_string(),
+        transaction: base64::encode(bincode::serialize(&tx)?),
+    };
+    let writer =
+        File::create(&opts.out).with_context(|| format!("unable to create {}", opts.out))?;
+    serde_json::to_writer_pretty(writer, &file)?;
+
+    Ok(SignedTransactionReport {
+        file: opts.out.clone(),
+        nonce_account: opts.nonce_account,
+        nonce_blockhash: nonce_blockhash.to_string(),
+        signature: tx.signatures[0].to_string(),
+    })
+}
+
+/// Submit a transaction written by `sign-nonce-transaction`.
+///
+/// Fails early if the nonce has been advanced since the transaction was signed,
+/// since the network would reject it anyway.
+pub fn submit_transaction(
+    config: &Config,
+    client: &RpcClient,
+    opts: &SubmitTransactionOpts,
+) -> Result<SubmitReport> {
+    let reader = File::open(&opts.file).with_context(|| format!("unable to open {}", opts.file))?;
+    let file: SignedTransactionFile = serde_json::from_reader(reader)
+        .with_context(|| format!("unable to parse {}", opts.file))?;
+    let nonce_account: Pubkey = file
+        .nonce_account
+        .parse()
+        .with_context(|| format!("invalid nonce account {:?}", file.nonce_account))?;
+    let tx: Transaction = bincode::deserialize(&base64::decode(&file.transaction)?)?;
+    tx.verify()
+        .context("transaction signatures do not verify")?;
+
+    let data = get_nonce_data(config, client, &nonce_account)?;
+    if data.blockhash != tx.message.recent_blockhash {
+        bail!(
+            "nonce account {} holds blockhash {}, but the transaction was signed with {}; \
+             the nonce has been used or advanced since",
+            nonce_account,
+            data.blockhash,
+            tx.message.recent_blockhash
+        );
+    }
+
+    let transaction = util::send_and_confirm(config, client, &tx)?;
+
+    // The next transaction must be signed with the advanced nonce.
+    let next_nonce_blockhash = if config.simulate.is_some() {
+        None
+    } else {
+        Some(get_nonce_data(config, client, &nonce_account)?.blockhash)
+    };
+
+    Ok(SubmitReport {
+        nonce_account,
+        next_nonce_blockhash: next_nonce_blockhash.map(|hash| hash.to_string()),
+        transaction,
+    })
+}
+
+fn get_nonce_data(
+    config: &Config,
+    client: &RpcClient,
+    nonce_account: &Pubkey,
+) -> Result<nonce::state::Data> {
+    let account =
+        nonce_utils::get_account_with_commitment(client, nonce_account, config.commitment)
+            .with_context(|| format!("unable to load nonce account {}", nonce_account))?;
+    let data = nonce_utils::data_from_account(&account)
+        .with_context(|| format!("{} is not an initialized nonce account", nonce_account))?;
+
+    Ok(data)
+}
+
+#[derive(Serialize, Debug)]
+pub struct NonceAccountReport {
+    #[serde(serialize_with = "output::display")]
+    pub nonce_account: Pubkey,
+    #[serde(serialize_with = "output::display")]
+    pub authority: Pubkey,
+    /// The blockhash to sign the first durable transaction with.
+    ///
+    /// Not known when simulating.
+    pub nonce_blockhash: Option<String>,
+    pub transaction: TransactionReport,
+}
+
+impl Report for NonceAccountReport {
+    fn print_text(&self) {
+        println!("nonce account: {}", self.nonce_account);
+        println!("authority: {}", self.authority);
+        if let Some(nonce_blockhash) = &self.nonce_blockhash {
+            println!("nonce blockhash: {}", nonce_blockhash);
+        }
+        self.transaction.print_text();
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct SignedTransactionReport {
+    pub file: String,
+    #[serde(serialize_with = "output::display")]
+    pub nonce_account: Pubkey,
+    pub nonce_blockhash: String,
+    pub signature: String,
+}
+
+impl Report for SignedTransactionReport {
+    fn print_text(&self) {
+        println!("wrote {}", self.file);
+        println!("nonce account: {}", self.nonce_account);
+        println!("nonce blockhash: {}", self.nonce_blockhash);
+        println!("sig: {}", self.signature);
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct SubmitReport {
+    #[serde(serialize_with = "output::display")]
+    pub nonce_account: Pubkey,
+    /// The blockhash to sign the next durable transaction with.
+    ///
+    /// Not known when simulating.
+    pub next_nonce_blockhash: Option<String>,
+    pub transaction: TransactionReport,
+}
+
+impl Report for SubmitReport {
+    fn print_text(&self) {
+        self.transaction.print_text();
+        if let Some(next_nonce_blockhash) = &self.next_nonce_blockhash {
+            println!("next nonce blockhash: {}", next_nonce_blockhash);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use crate::logs::{self, Invocation};
+use anyhow::Result;
+use serde::{Serialize, Serializer};
+use serde_json::json;
+use solana_sdk::{account::Account, pubkey::Pubkey, signature::Signature};
+use std::fmt::Display;
+
+#[derive(clap::ArgEnum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// The result of a command, printed once when it completes.
+///
+/// In JSON mode this is the only thing a command writes to stdout.
+pub trait Report: Serialize {
+    /// Print the report for humans.
+    fn print_text(&self);
+}
+
+pub fn print_report<R: Report>(format: OutputFormat, report: &R) -> Result<()> {
+    match format {
+        OutputFormat::Text => report.print_text(),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(report)?),
+    }
+
+    Ok(())
+}
+
+/// Print an error that ended a command.
+///
+/// In text mode errors are left to `main` to print.
+pub fn print_error(format: OutputFormat, error: &anyhow::Error) -> Result<()> {
+    if format == OutputFormat::Json {
+        let report = json!({ "error": format!("{:#}", error) });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    }
+
+    Ok(())
+}
+
+/// Print one notification of a streaming command.
+///
+/// Subscriptions don't end with a single document,
+/// so in JSON mode each notification is printed as one line of JSON.
+pub fn print_event<T: Serialize>(
+    format: OutputFormat,
+    subscription: &str,
+    event: &T,
+    print_text: impl FnOnce(&T),
+) {
+    match format {
+        OutputFormat::Text => print_text(event),
+        OutputFormat::Json => {
+            let event = json!({ "subscription": subscription, "event": event });
+            println!("{}", event);
+        }
+    }
+}
+
+/// Serialize with `Display`.
+///
+/// Pubkeys and signatures otherwise serialize as byte arrays.
+pub fn display<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+/// Serialize a pair with `Display`.
+fn display_pair<T: Display, S: Serializer>(
+    value: &(T, T),
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    (value.0.to_string(), value.1.to_string()).serialize(serializer)
+}
+
+/// A transaction sent by a demo, with the logs of each program it invoked.
+#[derive(Serialize, Debug)]
+pub struct TransactionReport {
+    /// What the transaction was for, when a demo sends more than one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(serialize_with = "display")]
+    pub signature: Signature,
+    /// Total compute units consumed by the top-level instructions.
+    pub units_consumed: u64,
+    pub invocations: Vec<Invocation>,
+    /// The raw log messages.
+    pub logs: Vec<String>,
+    /// Set when the transaction was simulated rather than sent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub simulation: Option<SimulationReport>,
+}
+
+/// What a simulated transaction would have done.
+#[derive(Serialize, Debug)]
+pub struct SimulationReport {
+    /// The transaction error, if the transaction would fail.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub err: Option<String>,
+    /// The accounts the transaction would change.
+    pub account_changes: Vec<AccountChange>,
+}
+
+/// The before and after state of an account that a simulation changed.
+#[derive(Serialize, Debug)]
+pub struct AccountChange {
+    #[serde(serialize_with = "display")]
+    pub pubkey: Pubkey,
+    pub lamports: (u64, u64),
+    pub data_len: (usize, usize),
+    #[serde(serialize_with = "display_pair")]
+    pub owner: (Pubkey, Pubkey),
+    pub data_changed: bool,
+}
+
+impl AccountChange {
+    /// Compare two states of an account, where a missing account has no lamports or data.
+    ///
+    /// Returns `None` if nothing changed.
+    pub fn new(
+        pubkey: Pubkey,
+        before: Option<&Account>,
+        after: Option<&Account>,
+    ) -> Option<AccountChange> {
+        let empty = Account::default();
+        let before = before.unwrap_or(&empty);
+        let after = after.unwrap_or(&empty);
+        let change = AccountChange {
+            pubkey,
+            lamports: (before.lamports, after.lamports),
+            data_len: (before.data.len(), after.data.len()),
+            owner: (before.owner, after.owner),
+            data_changed: before.data != after.data,
+        };
+
+        let changed = change.lamports.0 != change.lamports.1
+            || change.data_len.0 != change.data_len.1
+            || change.owner.0 != change.owner.1
+            || change.data_changed;
+
+        changed.then(|| change)
+    }
+
+    pub fn print_text(&self) {
+        println!("  account {}:", self.pubkey);
+        if self.lamports.0 != self.lamports.1 {
+            let delta = i128::from(self.lamports.1) - i128::from(self.lamports.0);
+            println!(
+                "    lamports: {} -> {} ({:+})",
+                self.lamports.0, self.lamports.1, delta
+            );
+        }
+        if self.data_len.0 != self.data_len.1 {
+            println!("    data len: {} -> {}", self.data_len.0, self.data_len.1);
+        }
+        if self.owner.0 != self.owner.1 {
+            println!("    owner: {} -> {}", self.owner.0, self.owner.1);
+        }
+        if self.data_changed {
+            println!("    data changed");
+        }
+    }
+}
+
+impl TransactionReport {
+    pub fn new(
+        signature: Signature,
+        logs: Vec<String>,
+        invocations: Vec<Invocation>,
+    ) -> TransactionReport {
+        TransactionReport {
+            label: None,
+            signature,
+            units_consumed: invocations.iter().filter_map(|i| i.units_consumed).sum(),
+            invocations,
+            logs,
+            simulation: None,
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> TransactionReport {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn print_text(&self) {
+        let simulated = if self.simulation.is_some() {
+            "simulated "
+        } else {
+            ""
+        };
+        match &self.label {
+            Some(label) => println!("{} {}sig: {}", label, simulated, self.signature),
+            None => println!("{}sig: {}", simulated, self.signature),
+        }
+        logs::print_invocations(&self.invocations, 2);
+        println!("  total: {} CU", self.units_consumed);
+
+        if let Some(simulation) = &self.simulation {
+            if let Some(err) = &simulation.err {
+                println!("  error: {}", err);
+            }
+            for change in &simulation.account_changes {
+                change.print_text();
+            }
+        }
+    }
+}
+
+/// The report of a demo that only sends transactions.
+#[derive(Serialize, Debug)]
+pub struct TransactionsReport {
+    pub transactions: Vec<TransactionReport>,
+}
+
+impl Report for TransactionsReport {
+    fn print_text(&self) {
+        for transaction in &self.transactions {
+            transaction.print_text();
+        }
+    }
+}
+
+impl From<TransactionReport> for TransactionsReport {
+    fn from(transaction: TransactionReport) -> TransactionsReport {
+        TransactionsReport {
+            transactions: vec![transaction],
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::output::TransactionsReport;
+use crate::util::{self, SimulateConfig};
+use anyhow::{bail, Result};
+use common::{
+    CheckPoliciesInstruction, Policy, ReadSysvarsInstruction, SettleInstruction, SysvarAccess,
+};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::Instruction, signature::Signer, system_instruction, transaction::Transaction,
+};
+
+/// Send transactions whose layouts satisfy the program's composition policies,
+/// then simulate layouts that violate them, which the program must reject.
+pub fn demo_transaction_policies(
+    config: &crate::util::Config,
+    client: &RpcClient,
+) -> Result<TransactionsReport> {
+    let payer = config.keypair().pubkey();
+    let program_id = config.program_id();
+
+    let check =
+        |policies: Vec<Policy>| CheckPoliciesInstruction { policies }.build_instruction(program_id);
+    let settle = |id: u64| SettleInstruction { id }.build_instruction(program_id);
+    let transfer_instr = system_instruction::transfer(&payer, &payer, 1);
+    let read_sysvars_instr = ReadSysvarsInstruction {
+        access: SysvarAccess::Syscalls,
+    }
+    .build_instruction(program_id);
+
+    // (label, instructions, permitted)
+    let transactions: Vec<(&str, Vec<Instruction>, bool)> = vec![
+        (
+            "last",
+            vec![transfer_instr.clone(), check(vec![Policy::MustBeLast])],
+            true,
+        ),
+        (
+            "not last",
+            vec![check(vec![Policy::MustBeLast]), transfer_instr.clone()],
+            false,
+        ),
+        (
+            "only instruction for program",
+            vec![
+                transfer_instr.clone(),
+                check(vec![Policy::NoOtherInstructionFor(*program_id)]),
+            ],
+            true,
+        ),
+        (
+            "other instruction for program",
+            vec![
+                check(vec![Policy::NoOtherInstructionFor(*program_id)]),
+                read_sysvars_instr,
+            ],
+            false,
+        ),
+        (
+            "settled",
+            vec![
+                check(vec![Policy::SettleFollows { id: 7 }]),
+                transfer_instr.clone(),
+                settle(7),
+            ],
+            true,
+        ),
+        (
+            "settled with wrong id",
+            vec![
+                check(vec![Policy::SettleFollows { id: 7 }]),
+                transfer_instr.clone(),
+                settle(8),
+            ],
+            false,
+        ),
+        (
+            "settled before",
+            vec![
+                settle(7),
+                transfer_instr,
+                check(vec![Policy::SettleFollows { id: 7 }]),
+            ],
+            false,
+        ),
+        (
+            "settled but not last",
+            vec![
+                check(vec![Policy::SettleFollows { id: 7 }, Policy::MustBeLast]),
+                settle(7),
+            ],
+            false,
+        ),
+    ];
+
+    let simulate = SimulateConfig {
+        sig_verify: false,
+        replace_blockhash: true,
+    };
+
+    let mut reports = vec![];
+
+    for (label, instrs, permitted) in transactions {
+        let blockhash = client.get_latest_blockhash()?;
+        let tx = Transaction::new_signed_with_payer(
+            &instrs,
+            Some(&payer),
+            &[config.keypair()],
+            blockhash,
+        );
+
+        // Rejected layouts are only simulated, to get their logs.
+        let transaction = if permitted {
+            util::send_and_confirm(config, client, &tx)?
+        } else {
+            let transaction = util::simulate_transaction(client, &tx, simulate)?;
+            let rejected = transaction
+                .simulation
+                .as_ref()
+                .map_or(false, |simulation| simulation.err.is_some());
+            if !rejected {
+                bail!("the program did not reject layout \"{}\"", label);
+            }
+            transaction
+        };
+
+        reports.push(transaction.with_label(label));
+    }
+
+    Ok(TransactionsReport {
+        transactions: reports,
+    })
+}
@@ -1,5 +1,7 @@
 #![allow(unused)]
 
+use crate::output::{self, TransactionReport};
+use crate::util;
 use anyhow::Result;
 use crossbeam_channel::unbounded;
 use solana_client::rpc_response::SlotInfo;
@@ -12,31 +14,25 @@ use solana_client::{
     },
 };
 use solana_sdk::{
-    commitment_config::{CommitmentConfig, CommitmentLevel},
     rent::Rent,
-    rpc_port,
     signature::{Keypair, Signer},
     system_program, system_transaction,
 };
 use std::thread;
 
-pub fn demo_pubsub_client(
-    config: &crate::util::Config,
-    rpc_client: &RpcClient,
-    program_keypair: &Keypair,
-) -> Result<()> {
-    //    let ws_url = "wss://api.devnet.solana.com/";
-    let ws_url = &format!("ws://127.0.0.1:{}/", rpc_port::DEFAULT_RPC_PUBSUB_PORT);
+pub fn demo_pubsub_client(config: &crate::util::Config, rpc_client: &RpcClient) -> Result<()> {
+    let ws_url = &config.websocket_url;
+    let format = config.output;
 
-    println!("-------------------- account subscription --------------------");
+    eprintln!("-------------------- account subscription --------------------");
     let rpc_config = Some(RpcAccountInfoConfig {
-        commitment: Some(CommitmentConfig::confirmed()),
+        commitment: Some(config.commitment),
         encoding: None,
         data_slice: None,
     });
 
     let (mut account_subscription_client, account_subscription_receiver) =
-        PubsubClient::account_subscribe(ws_url, &config.keypair.pubkey(), rpc_config.clone())?;
+        PubsubClient::account_subscribe(ws_url, &config.keypair().pubkey(), rpc_config.clone())?;
 
     let alice = Keypair::new();
     let (mut account_subscription_client_for_alice, account_subscription_receiver_for_alice) =
@@ -45,18 +41,29 @@ pub fn demo_pubsub_client(
     // send a tx for testing
     let blockhash = rpc_client.get_latest_blockhash()?;
     let transfer_amount = Rent::default().minimum_balance(0);
-    let tx =
-        system_transaction::transfer(&config.keypair, &alice.pubkey(), transfer_amount, blockhash);
-    let sig = rpc_client.send_and_confirm_transaction(&tx)?;
-    println!("transfer sig: {}", sig);
+    let tx = system_transaction::transfer(
+        config.keypair(),
+        &alice.pubkey(),
+        transfer_amount,
+        blockhash,
+    );
+    let transaction = util::send_and_confirm(config, rpc_client, &tx)?.with_label("transfer");
+    output::print_event(
+        format,
+        "transaction",
+        &transaction,
+        TransactionReport::print_text,
+    );
 
     thread::spawn(move || loop {
         match account_subscription_receiver.recv() {
             Ok(response) => {
-                println!("account subscription response: {:?}", response);
+                output::print_event(format, "account", &response, |response| {
+                    println!("account subscription response: {:?}", response);
+                });
             }
             Err(e) => {
-                println!("account subscription error: {:?}", e);
+                eprintln!("account subscription error: {:?}", e);
                 break;
             }
         }
@@ -65,29 +72,29 @@ pub fn demo_pubsub_client(
     thread::spawn(move || loop {
         match account_subscription_receiver_for_alice.recv() {
             Ok(response) => {
-                println!("account subscription for alice response: {:?}", response);
+                output::print_event(format, "account_for_alice", &response, |response| {
+                    println!("account subscription for alice response: {:?}", response);
+                });
             }
             Err(e) => {
-                println!("account subscription for alice error: {:?}", e);
+                eprintln!("account subscription for alice error: {:?}", e);
                 break;
             }
         }
     });
 
-    println!("-------------------- logs subscription --------------------");
+    eprintln!("-------------------- logs subscription --------------------");
     let (mut logs_subscription_client, logs_subscription_receiver) = PubsubClient::logs_subscribe(
         ws_url,
         RpcTransactionLogsFilter::All,
         RpcTransactionLogsConfig {
-            commitment: Some(CommitmentConfig {
-                commitment: CommitmentLevel::Confirmed,
-            }),
+            commitment: Some(config.commitment),
         },
     )?;
 
     thread::spawn(move || loop {
         match logs_subscription_receiver.recv() {
-            Ok(logs) => {
+            Ok(logs) => output::print_event(format, "logs", &logs, |logs| {
                 println!("---------- logs subscription result ----------");
                 println!("Transaction executed in slot {}:", logs.context.slot);
                 println!("  Signature: {}", logs.value.signature);
@@ -95,22 +102,23 @@ pub fn demo_pubsub_client(
                     "  Status: {}",
                     logs.value
                         .err
+                        .as_ref()
                         .map(|err| err.to_string())
                         .unwrap_or_else(|| "Ok".to_string())
                 );
                 println!("  Log Messages:");
-                for log in logs.value.logs {
+                for log in &logs.value.logs {
                     println!("    {}", log);
                 }
-            }
+            }),
             Err(e) => {
-                println!("log subscription error: {:?}", e);
+                eprintln!("log subscription error: {:?}", e);
                 break;
             }
         }
     });
 
-    println!("-------------------- program subscription --------------------");
+    eprintln!("-------------------- program subscription --------------------");
     let (mut program_subscription_client, program_subscription_receiver) =
         PubsubClient::program_subscribe(
             ws_url,
@@ -123,85 +131,102 @@ pub fn demo_pubsub_client(
     thread::spawn(move || loop {
         match program_subscription_receiver.recv() {
             Ok(response) => {
-                println!("program subscription response: {:?}", response);
+                output::print_event(format, "program", &response, |response| {
+                    println!("program subscription response: {:?}", response);
+                });
             }
             Err(e) => {
-                println!("program subscription error: {:?}", e);
+                eprintln!("program subscription error: {:?}", e);
                 break;
             }
         }
     });
 
-    println!("-------------------- root subscription --------------------");
+    eprintln!("-------------------- root subscription --------------------");
     let (mut root_subscription_client, root_subscription_receiver) =
         PubsubClient::root_subscribe(ws_url)?;
 
     thread::spawn(move || loop {
         match root_subscription_receiver.recv() {
             Ok(result) => {
-                println!("root subscription result: {:?}", result);
+                output::print_event(format, "root", &result, |result| {
+                    println!("root subscription result: {:?}", result);
+                });
             }
             Err(e) => {
-                println!("root subscription error: {:?}", e);
+                eprintln!("root subscription error: {:?}", e);
                 break;
             }
         }
     });
 
-    println!("-------------------- signature subscription --------------------");
+    eprintln!("-------------------- signature subscription --------------------");
     let alice = Keypair::new();
     let blockhash = rpc_client.get_latest_blockhash()?;
     let transfer_amount = Rent::default().minimum_balance(0);
-    let tx =
-        system_transaction::transfer(&config.keypair, &alice.pubkey(), transfer_amount, blockhash);
+    let tx = system_transaction::transfer(
+        config.keypair(),
+        &alice.pubkey(),
+        transfer_amount,
+        blockhash,
+    );
     let (mut sig_subscription_client, sig_subscription_receiver) =
         PubsubClient::signature_subscribe(
             ws_url,
             &tx.signatures[0],
             Some(RpcSignatureSubscribeConfig {
-                commitment: Some(CommitmentConfig::processed()),
+                commitment: Some(config.commitment),
                 enable_received_notification: Some(true),
             }),
         )?;
 
-    let sig = rpc_client.send_and_confirm_transaction(&tx)?;
-    println!("subscribe to signature: {:?}", sig);
+    let transaction = util::send_and_confirm(config, rpc_client, &tx)?.with_label("transfer");
+    output::print_event(
+        format,
+        "transaction",
+        &transaction,
+        TransactionReport::print_text,
+    );
 
     thread::spawn(move || loop {
         match sig_subscription_receiver.recv() {
             Ok(response) => {
-                println!("signature subscription response: {:?}", response);
+                output::print_event(format, "signature", &response, |response| {
+                    println!("signature subscription response: {:?}", response);
+                });
             }
             Err(e) => {
-                println!("signature subscription error: {:?}", e);
+                eprintln!("signature subscription error: {:?}", e);
                 break;
             }
         }
     });
 
-    println!("-------------------- slot subscription --------------------");
+    eprintln!("-------------------- slot subscription --------------------");
     let (mut slot_subscription_client, slot_subscription_receiver) =
         PubsubClient::slot_subscribe(ws_url)?;
 
     thread::spawn(move || loop {
         match slot_subscription_receiver.recv() {
             Ok(result) => {
-                println!("slot subscription result: {:?}", result);
+                output::print_event(format, "slot", &result, |result| {
+                    println!("slot subscription result: {:?}", result);
+                });
             }
             Err(e) => {
-                println!("slot subscription error: {:?}", e);
+                eprintln!("slot subscription error: {:?}", e);
                 break;
             }
         }
     });
 
-    println!("-------------------- slot updates subscription --------------------");
+    eprintln!("-------------------- slot updates subscription --------------------");
     let (slot_updates_subscription_sender, slot_updates_subscription_receiver) =
         unbounded::<SlotInfo>();
     let handler = move |message| match slot_updates_subscription_sender.send(message) {
         Ok(_) => (),
         Err(e) => {
-            println!("handler receive error: {:?}", e);
+            eprintln!("handler receive error: {:?}", e);
         }
     };
 
@@ -216,10 +241,12 @@ pub fn demo_pubsub_client(
     thread::spawn(move || loop {
         match slot_updates_subscription_receiver.recv() {
             Ok(result) => {
-                println!("slot updates subscription result: {:?}", result);
+                output::print_event(format, "slot_updates", &result, |result| {
+                    println!("slot updates subscription result: {:?}", result);
+                });
             }
             Err(e) => {
-                println!("slot updates subscription error: {:?}", e);
+                eprintln!("slot updates subscription error: {:?}", e);
                 break;
             }
         }
@@ -230,14 +257,12 @@ pub fn demo_pubsub_client(
         // Error: unexpected message format: {"error": Object({"code":
         // Number(-32601), "message": String("Method not found")}), "id":
         // Number(1), "jsonrpc": String("2.0")}
-        println!("-------------------- block subscription --------------------");
+        eprintln!("-------------------- block subscription --------------------");
         let (mut block_subscription_client, block_subscription_receiver) = PubsubClient::block_subscribe(
             ws_url,
             RpcBlockSubscribeFilter::All,
             Some(RpcBlockSubscribeConfig {
-                commitment: Some(CommitmentConfig {
-                    commitment: CommitmentLevel::Confirmed,
-                }),
+                commitment: Some(config.commitment),
                 encoding: Some(UiTransactionEncoding::Json),
                 transaction_details: Some(TransactionDetails::Signatures),
                 show_rewards: None,
@@ -249,10 +274,12 @@ pub fn demo_pubsub_client(
             loop {
                 match block_subscription_receiver.recv() {
                     Ok(result) => {
-                        println!("block subscription result: {:?}", result);
+                        output::print_event(format, "block", &result, |result| {
+                            println!("block subscription result: {:?}", result);
+                        });
                     }
                     Err(e) => {
-                        println!("block subscription error: {:?}", e);
+                        eprintln!("block subscription error: {:?}", e);
                         break;
                     }
                 }
@@ -262,17 +289,19 @@ pub fn demo_pubsub_client(
         block_subscription_client.send_unsubscribe();
         block_subscription_client.shutdown();
 
-        println!("-------------------- vote subscription --------------------");
+        eprintln!("-------------------- vote subscription --------------------");
         let (mut vote_subscription_client, vote_subscription_receiver) = PubsubClient::vote_subscribe(ws_url)?;
 
         thread::spawn(move || {
             loop {
                 match vote_subscription_receiver.recv() {
                     Ok(result) => {
-                        println!("vote subscription result: {:?}", result);
+                        output::print_event(format, "vote", &result, |result| {
+                            println!("vote subscription result: {:?}", result);
+                        });
                     }
                     Err(e) => {
-                        println!("vote subscription error: {:?}", e);
+                        eprintln!("vote subscription error: {:?}", e);
                         break;
                     }
                 }
@@ -287,7 +316,7 @@ pub fn demo_pubsub_client(
         thread::yield_now();
     }
 
-    println!("-------------------- clients unsubscribe and shutdown --------------------");
+    eprintln!("-------------------- clients unsubscribe and shutdown --------------------");
 
     account_subscription_client.send_unsubscribe();
     account_subscription_client.shutdown();
@@ -1,4 +1,5 @@
-use crate::util::Config;
+use crate::output::{self, TransactionReport};
+use crate::util::{self, Config};
 use anyhow::Result;
 use futures_util::StreamExt;
 use solana_client::{
@@ -11,12 +12,8 @@ use solana_client::{
     },
 };
 use solana_sdk::{
-    commitment_config::{CommitmentConfig, CommitmentLevel},
-    signature::Signature,
-    signature::Signer,
-    system_program, system_transaction,
-    sysvar::rent::Rent,
-    transaction::Transaction,
+    signature::Signature, signature::Signer, system_program, system_transaction,
+    sysvar::rent::Rent, transaction::Transaction,
 };
 use solana_transaction_status::{TransactionDetails, UiTransactionEncoding};
 use std::collections::HashSet;
@@ -32,13 +29,16 @@ use tokio::sync::mpsc::unbounded_channel;
 /// which subscribes and sends back a ready message and an unsubscribe channel (closure),
 /// then loops on printing messages.
 /// The main task then waits for user input before unsubscribing and waiting on the tasks.
+///
+/// With `--simulate` the test transactions are only simulated,
+/// so no notifications arrive for them.
 pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Result<()> {
     let rt = Runtime::new()?;
 
     rt.block_on(async move {
         let mut stdin = tokio::io::stdin();
 
-        println!("press any key to begin, then press another key to end");
+        eprintln!("press any key to begin, then press another key to end");
         stdin.read_u8().await?;
 
         // Subscription tasks will send a ready signal when they have subscribed.
@@ -57,7 +57,7 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
         let transactions: Vec<Transaction> = (0..5)
             .map(|_| {
                 system_transaction::transfer(
-                    &config.keypair,
+                    config.keypair(),
                     &solana_sdk::pubkey::new_rand(),
                     transfer_amount,
                     recent_blockhash,
@@ -67,7 +67,9 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
         let signatures: HashSet<Signature> =
             transactions.iter().map(|tx| tx.signatures[0]).collect();
 
-        let config_pubkey = config.keypair.pubkey();
+        let config_pubkey = config.keypair().pubkey();
+        let commitment = config.commitment;
+        let format = config.output;
 
         // The `PubsubClient` must be `Arc`ed to share it across tasks.
         let pubsub_client = Arc::new(PubsubClient::new(&config.websocket_url).await?);
@@ -101,8 +103,12 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
                     // Do something with the subscribed messages.
                     // This loop will end once the main task unsubscribes.
                     while let Some(slot_info) = slot_notifications.next().await {
-                        println!("------------------------------------------------------------");
-                        println!("slot pubsub result: {:?}", slot_info);
+                        output::print_event(format, "slot", &slot_info, |slot_info| {
+                            println!(
+                                "------------------------------------------------------------"
+                            );
+                            println!("slot pubsub result: {:?}", slot_info);
+                        });
                     }
 
                     // This type hint is necessary to allow the `async move` block to use `?`.
@@ -129,8 +135,17 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
                     drop((ready_sender, unsubscribe_sender));
 
                     while let Some(slot_updates) = slot_updates_notifications.next().await {
-                        println!("------------------------------------------------------------");
-                        println!("slot_updates pubsub result: {:?}", slot_updates);
+                        output::print_event(
+                            format,
+                            "slot_updates",
+                            &slot_updates,
+                            |slot_updates| {
+                                println!(
+                                    "------------------------------------------------------------"
+                                );
+                                println!("slot_updates pubsub result: {:?}", slot_updates);
+                            },
+                        );
                     }
 
                     Ok::<_, anyhow::Error>(())
@@ -149,9 +164,7 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
                         .logs_subscribe(
                             RpcTransactionLogsFilter::All,
                             RpcTransactionLogsConfig {
-                                commitment: Some(CommitmentConfig {
-                                    commitment: CommitmentLevel::Confirmed,
-                                }),
+                                commitment: Some(commitment),
                             },
                         )
                         .await?;
@@ -164,22 +177,27 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
                     drop((ready_sender, unsubscribe_sender));
 
                     while let Some(logs) = logs_notifications.next().await {
-                        println!("------------------------------------------------------------");
-                        println!("logs pubsub result:");
-
-                        println!("Transaction executed in slot {}:", logs.context.slot);
-                        println!("  Signature: {}", logs.value.signature);
-                        println!(
-                            "  Status: {}",
-                            logs.value
-                                .err
-                                .map(|err| err.to_string())
-                                .unwrap_or_else(|| "Ok".to_string())
-                        );
-                        println!("  Log Messages:");
-                        for log in logs.value.logs {
-                            println!("    {}", log);
-                        }
+                        output::print_event(format, "logs", &logs, |logs| {
+                            println!(
+                                "------------------------------------------------------------"
+                            );
+                            println!("logs pubsub result:");
+
+                            println!("Transaction executed in slot {}:", logs.context.slot);
+                            println!("  Signature: {}", logs.value.signature);
+                            println!(
+                                "  Status: {}",
+                                logs.value
+                                    .err
+                                    .as_ref()
+                                    .map(|err| err.to_string())
+                                    .unwrap_or_else(|| "Ok".to_string())
+                            );
+                            println!("  Log Messages:");
+                            for log in &logs.value.logs {
+                                println!("    {}", log);
+                            }
+                        });
                     }
 
                     Ok::<_, anyhow::Error>(())
@@ -205,8 +223,12 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
                     drop((ready_sender, unsubscribe_sender));
 
                     while let Some(root) = root_notifications.next().await {
-                        println!("------------------------------------------------------------");
-                        println!("root pubsub result: {:?}", root);
+                        output::print_event(format, "root", &root, |root| {
+                            println!(
+                                "------------------------------------------------------------"
+                            );
+                            println!("root pubsub result: {:?}", root);
+                        });
                     }
 
                     Ok::<_, anyhow::Error>(())
@@ -227,9 +249,7 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
                         .block_subscribe(
                             RpcBlockSubscribeFilter::All,
                             Some(RpcBlockSubscribeConfig {
-                                commitment: Some(CommitmentConfig {
-                                    commitment: CommitmentLevel::Processed, // Confirmed, Finalized
-                                }),
+                                commitment: Some(commitment),
                                 encoding: Some(UiTransactionEncoding::Json),
                                 transaction_details: Some(TransactionDetails::Signatures),
                                 show_rewards: None,
@@ -246,8 +266,12 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
                     drop((ready_sender, unsubscribe_sender));
 
                     while let Some(block) = block_notifications.next().await {
-                        println!("------------------------------------------------------------");
-                        println!("block pubsub result: {:?}", block);
+                        output::print_event(format, "block", &block, |block| {
+                            println!(
+                                "------------------------------------------------------------"
+                            );
+                            println!("block pubsub result: {:?}", block);
+                        });
                     }
 
                     Ok::<_, anyhow::Error>(())
@@ -280,8 +304,12 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
                     drop((ready_sender, unsubscribe_sender));
 
                     while let Some(program) = program_notifications.next().await {
-                        println!("------------------------------------------------------------");
-                        println!("program pubsub result: {:?}", program);
+                        output::print_event(format, "program", &program, |program| {
+                            println!(
+                                "------------------------------------------------------------"
+                            );
+                            println!("program pubsub result: {:?}", program);
+                        });
                     }
 
                     Ok::<_, anyhow::Error>(())
@@ -300,7 +328,7 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
                         .account_subscribe(
                             &config_pubkey,
                             Some(RpcAccountInfoConfig {
-                                commitment: Some(CommitmentConfig::confirmed()),
+                                commitment: Some(commitment),
                                 ..RpcAccountInfoConfig::default()
                             }),
                         )
@@ -314,8 +342,12 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
                     drop((ready_sender, unsubscribe_sender));
 
                     while let Some(account) = account_notifications.next().await {
-                        println!("------------------------------------------------------------");
-                        println!("account pubsub result: {:?}", account);
+                        output::print_event(format, "account", &account, |account| {
+                            println!(
+                                "------------------------------------------------------------"
+                            );
+                            println!("account pubsub result: {:?}", account);
+                        });
                     }
 
                     Ok::<_, anyhow::Error>(())
@@ -343,8 +375,12 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
                     drop((ready_sender, unsubscribe_sender));
 
                     while let Some(vote) = vote_notifications.next().await {
-                        println!("------------------------------------------------------------");
-                        println!("vote pubsub result: {:?}", vote);
+                        output::print_event(format, "vote", &vote, |vote| {
+                            println!(
+                                "------------------------------------------------------------"
+                            );
+                            println!("vote pubsub result: {:?}", vote);
+                        });
                     }
 
                     Ok::<_, anyhow::Error>(())
@@ -364,7 +400,7 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
                             .signature_subscribe(
                                 &signature,
                                 Some(RpcSignatureSubscribeConfig {
-                                    commitment: Some(CommitmentConfig::confirmed()),
+                                    commitment: Some(commitment),
                                     ..RpcSignatureSubscribeConfig::default()
                                 }),
                             )
@@ -378,10 +414,17 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
                         drop((ready_sender, unsubscribe_sender));
 
                         while let Some(sig_response) = signature_notifications.next().await {
-                            println!(
+                            output::print_event(
+                                format,
+                                "signature",
+                                &sig_response,
+                                |sig_response| {
+                                    println!(
                                 "------------------------------------------------------------"
                             );
-                            println!("signature pubsub result: {:?}", sig_response);
+                                    println!("signature pubsub result: {:?}", sig_response);
+                                },
+                            );
                         }
 
                         Ok::<_, anyhow::Error>(())
@@ -397,10 +440,16 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
         // Wait until all subscribers are ready.
         while let Some(_) = ready_receiver.recv().await {}
 
-        println!("sending test transactions");
+        eprintln!("sending test transactions");
         for tx in transactions {
-            let sig = rpc_client.send_and_confirm_transaction(&tx)?;
-            println!("transfer sig: {}", sig);
+            let transaction =
+                util::send_and_confirm(config, &rpc_client, &tx)?.with_label("transfer");
+            output::print_event(
+                format,
+                "transaction",
+                &transaction,
+                TransactionReport::print_text,
+            );
         }
 
         // Wait for input.
@@ -408,15 +457,15 @@ pub fn demo_pubsub_client_async(config: &Config, rpc_client: RpcClient) -> Resul
 
         // Unsubscribe from everything, which will shutdown all the tasks.
         while let Some((unsubscribe, name)) = unsubscribe_receiver.recv().await {
-            println!("unsubscribing from {}", name);
+            eprintln!("unsubscribing from {}", name);
             unsubscribe().await
         }
 
         // Wait for the tasks.
         for (name, handle) in join_handles {
-            println!("waiting on task {}", name);
+            eprintln!("waiting on task {}", name);
             if let Ok(Err(e)) = handle.await {
-                println!("task {} failed: {}", name, e);
+                eprintln!("task {} failed: {}", name, e);
             }
         }
 
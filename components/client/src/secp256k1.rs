@@ -1,8 +1,11 @@
-use anyhow::Result;
+use crate::output::{Report, TransactionReport, TransactionsReport};
+use crate::util;
+use anyhow::{bail, Result};
 use common::{
     DemoSecp256k1CustomManyInstruction, DemoSecp256k1RecoverInstruction,
-    DemoSecp256k1VerifyBasicInstruction,
+    DemoSecp256k1VerifyBasicInstruction, Secp256k1RecoverReturnData,
 };
+use serde::Serialize;
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{
     instruction::Instruction,
@@ -11,7 +14,7 @@ use solana_sdk::{
         self, SecpSignatureOffsets, HASHED_PUBKEY_SERIALIZED_SIZE,
         SIGNATURE_OFFSETS_SERIALIZED_SIZE, SIGNATURE_SERIALIZED_SIZE,
     },
-    signature::{Keypair, Signer},
+    signature::Signer,
     transaction::Transaction,
 };
 
@@ -27,8 +30,7 @@ const AUTHORIZED_SECRET_KEY: [u8; 32] = [
 pub fn demo_secp256k1_verify_basic(
     config: &crate::util::Config,
     client: &RpcClient,
-    program_keypair: &Keypair,
-) -> Result<()> {
+) -> Result<TransactionsReport> {
     let secret_key = libsecp256k1::SecretKey::parse(&AUTHORIZED_SECRET_KEY)?;
 
     // Internally to `new_secp256k1_instruction` and
@@ -37,21 +39,19 @@ pub fn demo_secp256k1_verify_basic(
     let msg = b"hello world";
     let secp256k1_instr = secp256k1_instruction::new_secp256k1_instruction(&secret_key, msg);
 
-    let program_instr =
-        DemoSecp256k1VerifyBasicInstruction.build_instruction(&program_keypair.pubkey());
+    let program_instr = DemoSecp256k1VerifyBasicInstruction.build_instruction(config.program_id());
 
     let blockhash = client.get_latest_blockhash()?;
     let tx = Transaction::new_signed_with_payer(
         &[secp256k1_instr, program_instr],
-        Some(&config.keypair.pubkey()),
-        &[&config.keypair],
+        Some(&config.keypair().pubkey()),
+        &[config.keypair()],
         blockhash,
     );
 
-    let sig = client.send_and_confirm_transaction(&tx)?;
-    println!("sig: {}", sig);
+    let transaction = util::send_and_confirm(config, client, &tx)?;
 
-    Ok(())
+    Ok(transaction.into())
 }
 
 /// Using the secp256k1 program in a more complex way,
@@ -59,10 +59,10 @@ pub fn demo_secp256k1_verify_basic(
 pub fn demo_secp256k1_custom_many(
     config: &crate::util::Config,
     client: &RpcClient,
-    program_keypair: &Keypair,
-) -> Result<()> {
+) -> Result<Secp256k1CustomManyReport> {
     // Sign some messages.
     let mut signatures = vec![];
+    let mut signature_reports = vec![];
     for idx in 0..2 {
         let secret_key = libsecp256k1::SecretKey::random(&mut rand::thread_rng());
         let message = format!("hello world {}", idx).into_bytes();
@@ -79,14 +79,12 @@ pub fn demo_secp256k1_custom_many(
         let public_key = libsecp256k1::PublicKey::from_secret_key(&secret_key);
         let eth_address = secp256k1_instruction::construct_eth_pubkey(&public_key);
 
-        let signature_hex = hex::encode(&signature);
-        let eth_address_hex = hex::encode(&eth_address);
-        let message_hex = hex::encode(&message);
-
-        println!("sig {}: {:?}", idx, signature_hex);
-        println!("recid {}: {}", idx, recovery_id);
-        println!("eth address {}: {}", idx, eth_address_hex);
-        println!("message {}: {}", idx, message_hex);
+        signature_reports.push(SecpSignatureReport {
+            signature: hex::encode(&signature),
+            recovery_id,
+            eth_address: hex::encode(&eth_address),
+            message: hex::encode(&message),
+        });
 
         signatures.push(SecpSignature {
             signature,
@@ -103,21 +101,49 @@ pub fn demo_secp256k1_custom_many(
         vec![],
     );
 
-    let program_instr =
-        DemoSecp256k1CustomManyInstruction.build_instruction(&program_keypair.pubkey());
+    let program_instr = DemoSecp256k1CustomManyInstruction.build_instruction(config.program_id());
 
     let blockhash = client.get_latest_blockhash()?;
     let tx = Transaction::new_signed_with_payer(
         &[secp256k1_instr, program_instr],
-        Some(&config.keypair.pubkey()),
-        &[&config.keypair],
+        Some(&config.keypair().pubkey()),
+        &[config.keypair()],
         blockhash,
     );
 
-    let sig = client.send_and_confirm_transaction(&tx)?;
-    println!("sig: {}", sig);
+    let transaction = util::send_and_confirm(config, client, &tx)?;
 
-    Ok(())
+    Ok(Secp256k1CustomManyReport {
+        signatures: signature_reports,
+        transaction,
+    })
+}
+
+#[derive(Serialize, Debug)]
+pub struct Secp256k1CustomManyReport {
+    pub signatures: Vec<SecpSignatureReport>,
+    pub transaction: TransactionReport,
+}
+
+/// A hex-encoded `SecpSignature`.
+#[derive(Serialize, Debug)]
+pub struct SecpSignatureReport {
+    pub signature: String,
+    pub recovery_id: u8,
+    pub eth_address: String,
+    pub message: String,
+}
+
+impl Report for Secp256k1CustomManyReport {
+    fn print_text(&self) {
+        for (idx, signature) in self.signatures.iter().enumerate() {
+            println!("sig {}: {:?}", idx, signature.signature);
+            println!("recid {}: {}", idx, signature.recovery_id);
+            println!("eth address {}: {}", idx, signature.eth_address);
+            println!("message {}: {}", idx, signature.message);
+        }
+        self.transaction.print_text();
+    }
 }
 
 /// A struct to hold the values specified in the `SecpSignatureOffsets` struct.
@@ -203,8 +229,29 @@ fn make_secp256k1_instruction_data(
 pub fn demo_secp256k1_recover(
     config: &crate::util::Config,
     client: &RpcClient,
-    program_keypair: &Keypair,
-) -> Result<()> {
+) -> Result<Secp256k1RecoverReport> {
+    let instr = recover_instruction()?.build_instruction(config.program_id());
+
+    let blockhash = client.get_latest_blockhash()?;
+    let tx = Transaction::new_signed_with_payer(
+        &[instr],
+        Some(&config.keypair().pubkey()),
+        &[config.keypair()],
+        blockhash,
+    );
+
+    let transaction = util::send_and_confirm(config, client, &tx)?;
+
+    let recovered_pubkey = check_recovered_pubkey(&transaction)?;
+
+    Ok(Secp256k1RecoverReport {
+        transactions: vec![transaction],
+        recovered_pubkey: hex::encode(recovered_pubkey),
+    })
+}
+
+/// Sign a message with the authorized key, for the program to recover.
+pub fn recover_instruction() -> Result<DemoSecp256k1RecoverInstruction> {
     let secret_key = libsecp256k1::SecretKey::parse(&AUTHORIZED_SECRET_KEY)?;
 
     let message = b"hello world";
@@ -221,25 +268,50 @@ pub fn demo_secp256k1_recover(
 
     assert_eq!(signature.len(), SIGNATURE_SERIALIZED_SIZE);
 
-    let instr = DemoSecp256k1RecoverInstruction {
+    Ok(DemoSecp256k1RecoverInstruction {
         message: message.to_vec(),
         signature,
         recovery_id: recovery_id.serialize(),
+    })
+}
+
+/// Decode the pubkey returned by the first instruction,
+/// and check it is the authorized key.
+pub fn check_recovered_pubkey(transaction: &TransactionReport) -> Result<[u8; 64]> {
+    let return_data: Option<Secp256k1RecoverReturnData> = match transaction.invocations.first() {
+        Some(invocation) => invocation.decode_return_data()?,
+        None => None,
+    };
+    let recovered_pubkey = match return_data {
+        Some(return_data) => return_data.pubkey,
+        None => bail!("expected return data"),
+    };
+
+    // The serialized public key is prefixed with 0x04, which the syscall omits.
+    let secret_key = libsecp256k1::SecretKey::parse(&AUTHORIZED_SECRET_KEY)?;
+    let expected_pubkey = libsecp256k1::PublicKey::from_secret_key(&secret_key).serialize();
+    if recovered_pubkey[..] != expected_pubkey[1..] {
+        bail!("the program recovered the wrong public key");
     }
-    .build_instruction(&program_keypair.pubkey());
 
-    let blockhash = client.get_latest_blockhash()?;
-    let tx = Transaction::new_signed_with_payer(
-        &[instr],
-        Some(&config.keypair.pubkey()),
-        &[&config.keypair],
-        blockhash,
-    );
+    Ok(recovered_pubkey)
+}
 
-    let sig = client.send_and_confirm_transaction(&tx)?;
-    println!("sig: {}", sig);
+#[derive(Serialize)]
+pub struct Secp256k1RecoverReport {
+    pub transactions: Vec<TransactionReport>,
+    /// The public key the program returned, hex encoded.
+    pub recovered_pubkey: String,
+}
 
-    Ok(())
+impl Report for Secp256k1RecoverReport {
+    fn print_text(&self) {
+        for transaction in &self.transactions {
+            transaction.print_text();
+        }
+
+        println!("recovered pubkey: {}", self.recovered_pubkey);
+    }
 }
 
 #[allow(unused)]
@@ -0,0 +1,380 @@
+use crate::logs;
+use crate::output::{self, Report, TransactionReport};
+use crate::util::{self, Config};
+use anyhow::{bail, Context, Result};
+use common::{
+    AccountState, CreateStateAccountsInstruction, InitializeStateInstruction, ReadStateInstruction,
+    ResizeAccountInstruction, StateSeed, UpdateStateInstruction,
+};
+use rand::Rng;
+use serde::Serialize;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    packet::PACKET_DATA_SIZE, pubkey::Pubkey, signature::Signer, transaction::Transaction,
+};
+
+/// The most accounts a transaction may reference.
+const MAX_ACCOUNT_LOCKS: usize = 64;
+
+#[derive(clap::Args)]
+pub struct ShowAccountOpts {
+    /// An account created by the program.
+    pub address: Pubkey,
+}
+
+#[derive(clap::Args)]
+pub struct ResizeAccountOpts {
+    /// A state account created by the wallet.
+    pub address: Pubkey,
+    /// The new data length, at least the length of the state.
+    #[clap(long)]
+    pub len: u64,
+}
+
+#[derive(clap::Args)]
+pub struct CreateStateAccountsOpts {
+    /// How many accounts to create, defaults to as many as fit in a transaction.
+    #[clap(long)]
+    pub count: Option<usize>,
+}
+
+/// Fetch an account of the program and decode its state.
+pub fn show_account(
+    config: &Config,
+    client: &RpcClient,
+    opts: &ShowAccountOpts,
+) -> Result<AccountStateReport> {
+    let account = client
+        .get_account_with_commitment(&opts.address, config.commitment)?
+        .value
+        .with_context(|| format!("account {} does not exist", opts.address))?;
+
+    if account.owner != *config.program_id() {
+        bail!(
+            "{} is owned by {}, not the program",
+            opts.address,
+            account.owner
+        );
+    }
+
+    let state = AccountState::decode(&account.data)
+        .with_context(|| format!("unable to decode {}", opts.address))?;
+
+    Ok(AccountStateReport::new(
+        opts.address,
+        account.lamports,
+        &state,
+    ))
+}
+
+/// Initialize a state account, update it, then read it back via return data.
+pub fn demo_account_state(config: &Config, client: &RpcClient) -> Result<DemoAccountStateReport> {
+    let payer = config.keypair().pubkey();
+    let program_id = config.program_id();
+
+    let state_seed: [u8; 16] = rand::thread_rng().gen();
+    let (state_pda, state_bump_seed) =
+        Pubkey::find_program_address(&[payer.as_ref(), &state_seed], program_id);
+
+    let initialize = InitializeStateInstruction {
+        payer,
+        state_pda,
+        state_seed,
+        state_bump_seed,
+        value: 1,
+    }
+    .build_instruction(program_id);
+    let update = UpdateStateInstruction {
+        creator: payer,
+        state_account: state_pda,
+        value: 2,
+    }
+    .build_instruction(program_id);
+    let read = ReadStateInstruction {
+        state_account: state_pda,
+    }
+    .build_instruction(program_id);
+
+    let mut transactions = vec![
+        util::send_instruction(config, client, initialize)?.with_label("initialize"),
+        util::send_instruction(config, client, update)?.with_label("update"),
+    ];
+
+    let transaction = util::send_instruction(config, client, read)?.with_label("read");
+    let state: Option<AccountState> = match transaction.invocations.first() {
+        Some(invocation) => invocation.decode_return_data()?,
+        None => None,
+    };
+    transactions.push(transaction);
+
+    // Simulated transactions don't see each other's changes,
+    // so the read may find nothing to return.
+    let state = match state {
+        Some(state) => Some(AccountStateReport::new(
+            state_pda,
+            client.get_balance(&state_pda)?,
+            &state,
+        )),
+        None if config.simulate.is_some() => None,
+        None => bail!("expected return data"),
+    };
+
+    Ok(DemoAccountStateReport {
+        transactions,
+        state,
+    })
+}
+
+/// Grow or shrink a state account, showing the balances before and after.
+pub fn resize_account(
+    config: &Config,
+    client: &RpcClient,
+    opts: &ResizeAccountOpts,
+) -> Result<ResizeReport> {
+    let payer = config.keypair().pubkey();
+
+    let before = Balances::get(config, client, &opts.address)?;
+
+    let instr = ResizeAccountInstruction {
+        payer,
+        state_account: opts.address,
+        new_len: opts.len,
+    }
+    .build_instruction(config.program_id());
+
+    let transaction = util::send_instruction(config, client, instr)?;
+
+    // When simulating, the transaction report shows the changes instead.
+    let after = match config.simulate {
+        Some(_) => None,
+        None => Some(Balances::get(config, client, &opts.address)?),
+    };
+
+    Ok(ResizeReport {
+        address: opts.address,
+        before,
+        after,
+        transaction,
+    })
+}
+
+/// Create many state accounts in one instruction,
+/// reporting the compute units each creation took.
+pub fn demo_create_state_accounts(
+    config: &Config,
+    client: &RpcClient,
+    opts: &CreateStateAccountsOpts,
+) -> Result<CreateStateAccountsReport> {
+    let payer = config.keypair().pubkey();
+    let program_id = config.program_id();
+
+    let max_count = max_batch_size(config);
+    let count = opts.count.unwrap_or(max_count);
+    if count > max_count {
+        bail!(
+            "{} accounts don't fit in a transaction, the most is {}",
+            count,
+            max_count
+        );
+    }
+
+    let seeds: Vec<_> = (0..count).map(|_| new_state_seed(config)).collect();
+    let accounts = seeds
+        .iter()
+        .map(|seed| seed.state_pda(&payer, program_id).to_string())
+        .collect();
+
+    let instr = CreateStateAccountsInstruction { payer, seeds }.build_instruction(program_id);
+    let transaction = util::send_instruction(config, client, instr)?;
+
+    let program_id = program_id.to_string();
+    let creation_units = transaction
+        .invocations
+        .iter()
+        .filter(|invocation| invocation.program_id == program_id)
+        .flat_map(|invocation| logs::checkpoint_costs(&logs::checkpoints(invocation)))
+        .filter(|cost| cost.label == "create")
+        .map(|cost| cost.units)
+        .collect();
+
+    Ok(CreateStateAccountsReport {
+        max_count,
+        accounts,
+        creation_units,
+        transaction,
+    })
+}
+
+fn new_state_seed(config: &Config) -> StateSeed {
+    let seed: [u8; 16] = rand::thread_rng().gen();
+    let (_, bump_seed) = Pubkey::find_program_address(
+        &[config.keypair().pubkey().as_ref(), &seed],
+        config.program_id(),
+    );
+
+    StateSeed { seed, bump_seed }
+}
+
+/// The most accounts `CreateStateAccountsInstruction` can create in one transaction.
+///
+/// Each account adds its key to the message and its seed to the instruction data,
+/// so this adds accounts until the transaction no longer fits in a packet,
+/// or references more accounts than a transaction may lock.
+fn max_batch_size(config: &Config) -> usize {
+    let payer = config.keypair().pubkey();
+    let mut seeds = vec![];
+
+    loop {
+        seeds.push(new_state_seed(config));
+
+        let instr = CreateStateAccountsInstruction {
+            payer,
+            seeds: seeds.clone(),
+        }
+        .build_instruction(config.program_id());
+        let tx = Transaction::new_with_payer(&[instr], Some(&payer));
+
+        let size = bincode::serialized_size(&tx).expect("transaction serializes") as usize;
+        if size > PACKET_DATA_SIZE || tx.message.account_keys.len() > MAX_ACCOUNT_LOCKS {
+            return seeds.len() - 1;
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct AccountStateReport {
+    #[serde(serialize_with = "output::display")]
+    pub address: Pubkey,
+    pub lamports: u64,
+    pub version: u8,
+    #[serde(serialize_with = "output::display")]
+    pub creator: Pubkey,
+    pub value: u64,
+    pub updates: u64,
+}
+
+impl AccountStateReport {
+    fn new(address: Pubkey, lamports: u64, state: &AccountState) -> AccountStateReport {
+        AccountStateReport {
+            address,
+            lamports,
+            version: state.version,
+            creator: state.creator,
+            value: state.value,
+            updates: state.updates,
+        }
+    }
+}
+
+impl Report for AccountStateReport {
+    fn print_text(&self) {
+        println!("address: {}", self.address);
+        println!("lamports: {}", self.lamports);
+        println!("version: {}", self.version);
+        println!("creator: {}", self.creator);
+        println!("value: {}", self.value);
+        println!("updates: {}", self.updates);
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct DemoAccountStateReport {
+    pub transactions: Vec<TransactionReport>,
+    /// The state the read instruction returned.
+    pub state: Option<AccountStateReport>,
+}
+
+impl Report for DemoAccountStateReport {
+    fn print_text(&self) {
+        for transaction in &self.transactions {
+            transaction.print_text();
+        }
+        if let Some(state) = &self.state {
+            state.print_text();
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Balances {
+    pub data_len: usize,
+    pub lamports: u64,
+    /// The rent-exempt minimum for `data_len`.
+    pub rent_exempt_minimum: u64,
+    pub payer_lamports: u64,
+}
+
+impl Balances {
+    fn get(config: &Config, client: &RpcClient, address: &Pubkey) -> Result<Balances> {
+        let account = client
+            .get_account_with_commitment(address, config.commitment)?
+            .value
+            .with_context(|| format!("account {} does not exist", address))?;
+
+        Ok(Balances {
+            data_len: account.data.len(),
+            lamports: account.lamports,
+            rent_exempt_minimum: client
+                .get_minimum_balance_for_rent_exemption(account.data.len())?,
+            payer_lamports: client.get_balance(&config.keypair().pubkey())?,
+        })
+    }
+
+    fn print_text(&self, label: &str) {
+        println!(
+            "{}: {} bytes, {} lamports (rent-exempt minimum {}), payer {} lamports",
+            label, self.data_len, self.lamports, self.rent_exempt_minimum, self.payer_lamports
+        );
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResizeReport {
+    #[serde(serialize_with = "output::display")]
+    pub address: Pubkey,
+    pub before: Balances,
+    pub after: Option<Balances>,
+    pub transaction: TransactionReport,
+}
+
+impl Report for ResizeReport {
+    fn print_text(&self) {
+        println!("account: {}", self.address);
+        self.before.print_text("before");
+        if let Some(after) = &self.after {
+            after.print_text("after");
+        }
+        self.transaction.print_text();
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct CreateStateAccountsReport {
+    /// The most accounts that fit in a transaction.
+    pub max_count: usize,
+    pub accounts: Vec<String>,
+    /// The compute units of each creation, in order.
+    pub creation_units: Vec<u64>,
+    pub transaction: TransactionReport,
+}
+
+impl Report for CreateStateAccountsReport {
+    fn print_text(&self) {
+        println!(
+            "created {} accounts, at most {} fit in a transaction",
+            self.accounts.len(),
+            self.max_count
+        );
+        for (account, units) in self.accounts.iter().zip(&self.creation_units) {
+            println!("  {} ({} CU)", account, units);
+        }
+        if !self.creation_units.is_empty() {
+            let total: u64 = self.creation_units.iter().sum();
+            println!(
+                "average: {} CU per account",
+                total / self.creation_units.len() as u64
+            );
+        }
+        self.transaction.print_text();
+    }
+}
@@ -1,83 +1,502 @@
-use anyhow::Result;
+use crate::output::{self, Report, TransactionReport};
+use crate::util;
+use anyhow::{bail, Result};
 use common::{
-    DemoSystemProgramCreateAccountInstruction, DemoSystemProgramTransferAllocAssignInstruction,
+    AccountState, DemoSystemProgramAllocateWithSeedInstruction,
+    DemoSystemProgramAssignWithSeedInstruction, DemoSystemProgramCreateAccountInstruction,
+    DemoSystemProgramCreateAccountWithSeedInstruction, DemoSystemProgramNonceInstruction,
+    DemoSystemProgramTransferAllocAssignInstruction, DemoSystemProgramTransferWithSeedInstruction,
+    NonceAction, SeedAddress,
 };
+use log::info;
 use rand::Rng;
-use solana_client::rpc_client::RpcClient;
+use serde::Serialize;
+use solana_client::{nonce_utils, rpc_client::RpcClient};
 use solana_sdk::{
-    pubkey::Pubkey,
-    signature::{Keypair, Signer},
-    transaction::Transaction,
+    instruction::Instruction, nonce, pubkey::Pubkey, signature::Signer, system_instruction,
+    system_program, transaction::Transaction,
 };
 
+/// The seed passed to `Pubkey::create_with_seed`.
+///
+/// The base is a new PDA each time, so the derived address is too.
+const SEED: &str = "demo";
+
+/// Lamports to put in the nonce account above its rent exemption, then withdraw.
+const NONCE_WITHDRAW_LAMPORTS: u64 = 1_000_000;
+
 pub fn demo_system_program_create_account_cpi(
     config: &crate::util::Config,
     client: &RpcClient,
-    program_keypair: &Keypair,
-) -> Result<()> {
+) -> Result<NewAccountReport> {
     let new_account_seed: [u8; 16] = rand::thread_rng().gen();
     let (new_account_pda, new_account_bump_seed) = Pubkey::find_program_address(
-        &[config.keypair.pubkey().as_ref(), &new_account_seed],
-        &program_keypair.pubkey(),
+        &[config.keypair().pubkey().as_ref(), &new_account_seed],
+        config.program_id(),
     );
 
     let instr = DemoSystemProgramCreateAccountInstruction {
-        payer: config.keypair.pubkey(),
+        payer: config.keypair().pubkey(),
         new_account_pda,
         new_account_seed,
         new_account_bump_seed,
     }
-    .build_instruction(&program_keypair.pubkey());
+    .build_instruction(config.program_id());
 
     let blockhash = client.get_latest_blockhash()?;
 
     let tx = Transaction::new_signed_with_payer(
         &[instr],
-        Some(&config.keypair.pubkey()),
-        &[&config.keypair],
+        Some(&config.keypair().pubkey()),
+        &[config.keypair()],
         blockhash,
     );
 
-    println!("new account: {}", new_account_pda);
+    let transaction = util::send_and_confirm(config, client, &tx)?;
 
-    let sig = client.send_and_confirm_transaction(&tx)?;
-    println!("sig: {}", sig);
-
-    Ok(())
+    Ok(NewAccountReport {
+        new_account: new_account_pda,
+        transaction,
+    })
 }
 
 pub fn demo_system_program_transfer_alloc_assign_cpi(
     config: &crate::util::Config,
     client: &RpcClient,
-    program_keypair: &Keypair,
-) -> Result<()> {
+) -> Result<NewAccountReport> {
     let new_account_seed: [u8; 16] = rand::thread_rng().gen();
     let (new_account_pda, new_account_bump_seed) = Pubkey::find_program_address(
-        &[config.keypair.pubkey().as_ref(), &new_account_seed],
-        &program_keypair.pubkey(),
+        &[config.keypair().pubkey().as_ref(), &new_account_seed],
+        config.program_id(),
     );
 
     let instr = DemoSystemProgramTransferAllocAssignInstruction {
-        payer: config.keypair.pubkey(),
+        payer: config.keypair().pubkey(),
         new_account_pda,
         new_account_seed,
         new_account_bump_seed,
     }
-    .build_instruction(&program_keypair.pubkey());
+    .build_instruction(config.program_id());
 
     let blockhash = client.get_latest_blockhash()?;
 
     let tx = Transaction::new_signed_with_payer(
         &[instr],
-        Some(&config.keypair.pubkey()),
-        &[&config.keypair],
+        Some(&config.keypair().pubkey()),
+        &[config.keypair()],
         blockhash,
     );
 
-    println!("new account: {}", new_account_pda);
+    let transaction = util::send_and_confirm(config, client, &tx)?;
+
+    Ok(NewAccountReport {
+        new_account: new_account_pda,
+        transaction,
+    })
+}
+
+#[derive(Serialize, Debug)]
+pub struct NewAccountReport {
+    #[serde(serialize_with = "output::display")]
+    pub new_account: Pubkey,
+    pub transaction: TransactionReport,
+}
+
+impl Report for NewAccountReport {
+    fn print_text(&self) {
+        println!("new account: {}", self.new_account);
+        self.transaction.print_text();
+    }
+}
+
+pub fn demo_system_program_create_account_with_seed_cpi(
+    config: &crate::util::Config,
+    client: &RpcClient,
+) -> Result<NewAccountReport> {
+    let seed_address = seed_address(config, config.program_id())?;
+    let new_account = seed_address.address;
+
+    let instr = DemoSystemProgramCreateAccountWithSeedInstruction { seed_address }
+        .build_instruction(config.program_id());
+
+    let transaction = util::send_instruction(config, client, instr)?;
+
+    Ok(NewAccountReport {
+        new_account,
+        transaction,
+    })
+}
+
+pub fn demo_system_program_allocate_with_seed_cpi(
+    config: &crate::util::Config,
+    client: &RpcClient,
+) -> Result<NewAccountReport> {
+    let seed_address = seed_address(config, config.program_id())?;
+    let new_account = seed_address.address;
+
+    let instr = DemoSystemProgramAllocateWithSeedInstruction { seed_address }
+        .build_instruction(config.program_id());
+
+    let transaction = util::send_instruction(config, client, instr)?;
+
+    Ok(NewAccountReport {
+        new_account,
+        transaction,
+    })
+}
+
+pub fn demo_system_program_assign_with_seed_cpi(
+    config: &crate::util::Config,
+    client: &RpcClient,
+) -> Result<NewAccountReport> {
+    let seed_address = seed_address(config, config.program_id())?;
+    let new_account = seed_address.address;
+
+    let instr = DemoSystemProgramAssignWithSeedInstruction { seed_address }
+        .build_instruction(config.program_id());
+
+    let transaction = util::send_instruction(config, client, instr)?;
+
+    Ok(NewAccountReport {
+        new_account,
+        transaction,
+    })
+}
+
+pub fn demo_system_program_transfer_with_seed_cpi(
+    config: &crate::util::Config,
+    client: &RpcClient,
+) -> Result<NewAccountReport> {
+    // The system program can only debit accounts it owns.
+    let seed_address = seed_address(config, &system_program::ID)?;
+    let new_account = seed_address.address;
+
+    let instr = DemoSystemProgramTransferWithSeedInstruction { seed_address }
+        .build_instruction(config.program_id());
+
+    let transaction = util::send_instruction(config, client, instr)?;
+
+    Ok(NewAccountReport {
+        new_account,
+        transaction,
+    })
+}
 
-    let sig = client.send_and_confirm_transaction(&tx)?;
-    println!("sig: {}", sig);
+/// Create PDAs whose addresses someone has already sent lamports to.
+///
+/// Anyone can send lamports to an address, which would make `create_account` fail.
+/// Each transaction here pre-funds a new PDA then creates it,
+/// and the last creates the same PDA twice to show that creating is idempotent.
+pub fn demo_system_program_prefunded_cpi(
+    config: &crate::util::Config,
+    client: &RpcClient,
+) -> Result<PrefundedReport> {
+    let payer = config.keypair().pubkey();
+    let program_id = config.program_id();
+
+    // Enough for an empty system account, but short of what the PDA needs.
+    let partial = client.get_minimum_balance_for_rent_exemption(0)?;
+    // More than the PDA needs.
+    let full = client.get_minimum_balance_for_rent_exemption(AccountState::LEN)? + 1;
+
+    let scenarios: [(&str, Option<u64>, BuildInstruction, usize); 4] = [
+        (
+            "create-prefunded-partial",
+            Some(partial),
+            create_account_instruction,
+            1,
+        ),
+        (
+            "create-prefunded-full",
+            Some(full),
+            create_account_instruction,
+            1,
+        ),
+        (
+            "transfer-alloc-assign-prefunded-partial",
+            Some(partial),
+            transfer_alloc_assign_instruction,
+            1,
+        ),
+        ("create-twice", None, create_account_instruction, 2),
+    ];
+
+    let mut transactions = vec![];
+    let mut accounts = vec![];
+
+    for (label, prefund, build_instruction, times) in scenarios {
+        let new_account_seed: [u8; 16] = rand::thread_rng().gen();
+        let (new_account_pda, new_account_bump_seed) =
+            Pubkey::find_program_address(&[payer.as_ref(), &new_account_seed], program_id);
+
+        let mut instrs = vec![];
+        if let Some(lamports) = prefund {
+            instrs.push(system_instruction::transfer(
+                &payer,
+                &new_account_pda,
+                lamports,
+            ));
+        }
+        for _ in 0..times {
+            instrs.push(build_instruction(
+                payer,
+                (new_account_pda, new_account_seed, new_account_bump_seed),
+                program_id,
+            ));
+        }
+
+        let blockhash = client.get_latest_blockhash()?;
+
+        let tx = Transaction::new_signed_with_payer(
+            &instrs,
+            Some(&payer),
+            &[config.keypair()],
+            blockhash,
+        );
+
+        let transaction = util::send_and_confirm(config, client, &tx)?;
+        if let Some(simulation) = &transaction.simulation {
+            if let Some(err) = &simulation.err {
+                bail!("{} failed: {}", label, err);
+            }
+        } else {
+            check_created_account(client, &new_account_pda, program_id)?;
+        }
+
+        transactions.push(transaction.with_label(label));
+        accounts.push(new_account_pda);
+    }
+
+    Ok(PrefundedReport {
+        accounts: accounts.iter().map(Pubkey::to_string).collect(),
+        transactions,
+    })
+}
+
+/// Builds an instruction that creates the given PDA.
+type BuildInstruction = fn(Pubkey, (Pubkey, [u8; 16], u8), &Pubkey) -> Instruction;
+
+fn create_account_instruction(
+    payer: Pubkey,
+    (new_account_pda, new_account_seed, new_account_bump_seed): (Pubkey, [u8; 16], u8),
+    program_id: &Pubkey,
+) -> Instruction {
+    DemoSystemProgramCreateAccountInstruction {
+        payer,
+        new_account_pda,
+        new_account_seed,
+        new_account_bump_seed,
+    }
+    .build_instruction(program_id)
+}
+
+fn transfer_alloc_assign_instruction(
+    payer: Pubkey,
+    (new_account_pda, new_account_seed, new_account_bump_seed): (Pubkey, [u8; 16], u8),
+    program_id: &Pubkey,
+) -> Instruction {
+    DemoSystemProgramTransferAllocAssignInstruction {
+        payer,
+        new_account_pda,
+        new_account_seed,
+        new_account_bump_seed,
+    }
+    .build_instruction(program_id)
+}
+
+/// Check the account ended up rent-exempt, allocated and owned by the program.
+fn check_created_account(client: &RpcClient, pubkey: &Pubkey, program_id: &Pubkey) -> Result<()> {
+    let account = client.get_account(pubkey)?;
+    let rent_exempt = client.get_minimum_balance_for_rent_exemption(AccountState::LEN)?;
+
+    if account.owner != *program_id {
+        bail!("{} is owned by {}", pubkey, account.owner);
+    }
+    if account.data.len() != AccountState::LEN {
+        bail!("{} has {} bytes of data", pubkey, account.data.len());
+    }
+    if account.lamports < rent_exempt {
+        bail!(
+            "{} has {} lamports, needs {}",
+            pubkey,
+            account.lamports,
+            rent_exempt
+        );
+    }
 
     Ok(())
 }
+
+/// Derive a new base PDA, and the address `Pubkey::create_with_seed` derives from it.
+///
+/// `owner` must be the owner the address will have.
+fn seed_address(config: &crate::util::Config, owner: &Pubkey) -> Result<SeedAddress> {
+    let payer = config.keypair().pubkey();
+    let base_seed: [u8; 16] = rand::thread_rng().gen();
+    let (base_pda, base_bump_seed) =
+        Pubkey::find_program_address(&[payer.as_ref(), &base_seed], config.program_id());
+    let address = Pubkey::create_with_seed(&base_pda, SEED, owner)?;
+
+    Ok(SeedAddress {
+        payer,
+        base_pda,
+        address,
+        base_seed,
+        base_bump_seed,
+        seed: SEED.to_string(),
+    })
+}
+
+/// Create a nonce account whose address and authority are PDAs of the program,
+/// then advance it, withdraw from it, and hand its authority to the payer.
+///
+/// With `--simulate` only the creation is simulated,
+/// since the other steps need the nonce account to exist.
+pub fn demo_system_program_nonce_cpi(
+    config: &crate::util::Config,
+    client: &RpcClient,
+) -> Result<NonceReport> {
+    let payer = config.keypair().pubkey();
+    let program_id = config.program_id();
+
+    let nonce_account_seed: [u8; 16] = rand::thread_rng().gen();
+    let (nonce_account_pda, nonce_account_bump_seed) =
+        Pubkey::find_program_address(&[payer.as_ref(), &nonce_account_seed], program_id);
+    let (nonce_authority_pda, nonce_authority_bump_seed) =
+        Pubkey::find_program_address(&[nonce_account_pda.as_ref()], program_id);
+
+    let lamports = client.get_minimum_balance_for_rent_exemption(nonce::State::size())?
+        + NONCE_WITHDRAW_LAMPORTS;
+
+    let actions = [
+        ("create", NonceAction::Create { lamports }),
+        ("advance", NonceAction::Advance),
+        (
+            "withdraw",
+            NonceAction::Withdraw {
+                lamports: NONCE_WITHDRAW_LAMPORTS,
+            },
+        ),
+        (
+            "authorize",
+            NonceAction::Authorize {
+                new_authority: payer,
+            },
+        ),
+    ];
+
+    let mut transactions = vec![];
+    let mut states = vec![];
+    let mut last_blockhash = None;
+
+    for (label, action) in actions {
+        let blockhash = match last_blockhash {
+            // Advancing fails unless the blockhash has changed
+            // since the nonce was stored.
+            Some(blockhash) => client.get_new_latest_blockhash(&blockhash)?,
+            None => client.get_latest_blockhash()?,
+        };
+        last_blockhash = Some(blockhash);
+
+        let instr = DemoSystemProgramNonceInstruction {
+            payer,
+            nonce_account_pda,
+            nonce_authority_pda,
+            nonce_account_seed,
+            nonce_account_bump_seed,
+            nonce_authority_bump_seed,
+            action,
+        }
+        .build_instruction(program_id);
+
+        let tx = Transaction::new_signed_with_payer(
+            &[instr],
+            Some(&payer),
+            &[config.keypair()],
+            blockhash,
+        );
+
+        let transaction = util::send_and_confirm(config, client, &tx)?;
+        let simulated = transaction.simulation.is_some();
+        transactions.push(transaction.with_label(label));
+
+        if simulated {
+            info!("nonce account not created by simulation, skipping the remaining steps");
+            break;
+        }
+
+        let account = nonce_utils::get_account_with_commitment(
+            client,
+            &nonce_account_pda,
+            config.commitment,
+        )?;
+        let data = nonce_utils::data_from_account(&account)?;
+        states.push(NonceState {
+            label,
+            blockhash: data.blockhash.to_string(),
+            authority: data.authority,
+            lamports: account.lamports,
+        });
+    }
+
+    Ok(NonceReport {
+        nonce_account: nonce_account_pda,
+        nonce_authority: nonce_authority_pda,
+        transactions,
+        states,
+    })
+}
+
+#[derive(Serialize, Debug)]
+pub struct NonceReport {
+    #[serde(serialize_with = "output::display")]
+    pub nonce_account: Pubkey,
+    #[serde(serialize_with = "output::display")]
+    pub nonce_authority: Pubkey,
+    pub transactions: Vec<TransactionReport>,
+    /// The nonce account after each transaction, empty when simulated.
+    pub states: Vec<NonceState>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct NonceState {
+    pub label: &'static str,
+    pub blockhash: String,
+    #[serde(serialize_with = "output::display")]
+    pub authority: Pubkey,
+    pub lamports: u64,
+}
+
+impl Report for NonceReport {
+    fn print_text(&self) {
+        println!("nonce account: {}", self.nonce_account);
+        println!("nonce authority: {}", self.nonce_authority);
+        for transaction in &self.transactions {
+            transaction.print_text();
+        }
+
+        for state in &self.states {
+            println!(
+                "after {}: blockhash {}, authority {}, {} lamports",
+                state.label, state.blockhash, state.authority, state.lamports
+            );
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct PrefundedReport {
+    pub accounts: Vec<String>,
+    pub transactions: Vec<TransactionReport>,
+}
+
+impl Report for PrefundedReport {
+    fn print_text(&self) {
+        for account in &self.accounts {
+            println!("created account: {}", account);
+        }
+        for transaction in &self.transactions {
+            transaction.print_text();
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use crate::logs;
+use crate::output::{Report, TransactionReport};
+use crate::util;
+use anyhow::{anyhow, Result};
+use common::{ReadSysvarsInstruction, SysvarAccess};
+use serde::Serialize;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{signature::Signer, transaction::Transaction};
+
+const SYSVARS: [&str; 7] = [
+    "clock",
+    "epoch_schedule",
+    "instructions",
+    "rent",
+    "slot_hashes",
+    "slot_history",
+    "stake_history",
+];
+
+/// Read the sysvars from accounts in one transaction,
+/// and via syscalls in another,
+/// and compare the compute units each sysvar cost.
+pub fn compare_sysvar_access(
+    config: &crate::util::Config,
+    client: &RpcClient,
+) -> Result<SysvarAccessReport> {
+    let (accounts_tx, accounts_costs, _) = read_sysvars(config, client, SysvarAccess::Accounts)?;
+    let (syscalls_tx, syscalls_costs, unsupported) =
+        read_sysvars(config, client, SysvarAccess::Syscalls)?;
+
+    let sysvars = SYSVARS
+        .iter()
+        .map(|&sysvar| {
+            let accounts = find_cost(&accounts_costs, sysvar);
+            let syscalls = find_cost(&syscalls_costs, sysvar);
+            let syscall_error = unsupported
+                .iter()
+                .find(|(name, _)| name == sysvar)
+                .map(|(_, reason)| reason.clone());
+            let difference = match (accounts, syscalls) {
+                (Some(accounts), Some(syscalls)) => Some(syscalls as i64 - accounts as i64),
+                _ => None,
+            };
+
+            SysvarAccessCost {
+                sysvar,
+                accounts,
+                syscalls,
+                difference,
+                syscall_error,
+            }
+        })
+        .collect();
+
+    Ok(SysvarAccessReport {
+        transactions: vec![
+            accounts_tx.with_label("Accounts"),
+            syscalls_tx.with_label("Syscalls"),
+        ],
+        sysvars,
+    })
+}
+
+/// Send one `ReadSysvars` instruction and parse its checkpoints,
+/// and any sysvars it couldn't read.
+fn read_sysvars(
+    config: &crate::util::Config,
+    client: &RpcClient,
+    access: SysvarAccess,
+) -> Result<(
+    TransactionReport,
+    Vec<logs::CheckpointCost>,
+    Vec<(String, String)>,
+)> {
+    let instr = ReadSysvarsInstruction { access }.build_instruction(config.program_id());
+
+    let blockhash = client.get_latest_blockhash()?;
+    let tx = Transaction::new_signed_with_payer(
+        &[instr],
+        Some(&config.keypair().pubkey()),
+        &[config.keypair()],
+        blockhash,
+    );
+
+    let transaction = util::send_and_confirm(config, client, &tx)?;
+
+    let program_id = config.program_id().to_string();
+    let invocation = transaction
+        .invocations
+        .iter()
+        .find(|invocation| invocation.program_id == program_id)
+        .ok_or_else(|| anyhow!("no logs from the program for {:?}", access))?;
+
+    let costs = logs::checkpoint_costs(&logs::checkpoints(invocation));
+    let unsupported = invocation
+        .logs
+        .iter()
+        .filter_map(|log| log.strip_prefix("unsupported: "))
+        .filter_map(|rest| rest.split_once(": "))
+        .map(|(sysvar, reason)| (sysvar.to_string(), reason.to_string()))
+        .collect();
+
+    Ok((transaction, costs, unsupported))
+}
+
+fn find_cost(costs: &[logs::CheckpointCost], sysvar: &str) -> Option<u64> {
+    costs
+        .iter()
+        .find(|cost| cost.label == sysvar)
+        .map(|cost| cost.units)
+}
+
+#[derive(Serialize, Debug)]
+pub struct SysvarAccessReport {
+    pub transactions: Vec<TransactionReport>,
+    pub sysvars: Vec<SysvarAccessCost>,
+}
+
+/// The compute units one sysvar cost to read each way.
+#[derive(Serialize, Debug)]
+pub struct SysvarAccessCost {
+    pub sysvar: &'static str,
+    pub accounts: Option<u64>,
+    pub syscalls: Option<u64>,
+    /// Syscall units minus account units.
+    pub difference: Option<i64>,
+    /// Why the sysvar can't be read via syscall.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub syscall_error: Option<String>,
+}
+
+impl Report for SysvarAccessReport {
+    fn print_text(&self) {
+        for transaction in &self.transactions {
+            transaction.print_text();
+        }
+
+        let units = |units: Option<u64>| {
+            units
+                .map(|u| u.to_string())
+                .unwrap_or_else(|| "-".to_string())
+        };
+
+        println!(
+            "{:<16} {:>10} {:>10} {:>10}",
+            "sysvar", "accounts", "syscalls", "difference"
+        );
+        for cost in &self.sysvars {
+            let difference = cost
+                .difference
+                .map(|d| format!("{:+}", d))
+                .unwrap_or_else(|| "-".to_string());
+            println!(
+                "{:<16} {:>10} {:>10} {:>10}",
+                cost.sysvar,
+                units(cost.accounts),
+                units(cost.syscalls),
+                difference
+            );
+        }
+        for cost in &self.sysvars {
+            if let Some(error) = &cost.syscall_error {
+                println!("{}: no syscall: {}", cost.sysvar, error);
+            }
+        }
+    }
+}
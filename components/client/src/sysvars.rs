@@ -1,141 +1,952 @@
-use anyhow::Result;
+use crate::logs;
+use crate::output::{self, Report, TransactionReport};
+use crate::util::{self, SimulateConfig};
+use anyhow::{anyhow, bail, Context, Result};
 use bincode;
 use common::{PrintSysvarsInstruction, PrintSysvarsSection};
+use log::info;
+use serde::Serialize;
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{
-    signature::{Keypair, Signer},
+    account::Account,
+    clock::{Epoch, Slot},
+    compute_budget::{self, ComputeBudgetInstruction},
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::Signer,
+    sysvar::{
+        clock::Clock, epoch_schedule::EpochSchedule, rent::Rent, slot_hashes::SlotHashes,
+        slot_history::SlotHistory, stake_history::StakeHistory,
+    },
     transaction::Transaction,
 };
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::str::FromStr;
 
+/// The most compute units a transaction can request.
+const MAX_COMPUTE_UNITS: u32 = 1_400_000;
+
+/// Added to the measured section costs,
+/// since the logs, and so the cost of logging them, vary a little between runs.
+const MEASUREMENT_HEADROOM_PERCENT: u64 = 5;
+
+const SECTIONS: [PrintSysvarsSection; 3] = [
+    PrintSysvarsSection::One,
+    PrintSysvarsSection::Two,
+    PrintSysvarsSection::Three,
+];
+
+#[derive(clap::Args)]
+pub struct PrintSysvarsOpts {
+    /// Print every section in one transaction,
+    /// requesting more compute units from the compute budget program.
+    #[clap(long)]
+    pub single_transaction: bool,
+    /// Compute units to request for the single transaction, 1400000 by default.
+    #[clap(long)]
+    pub compute_units: Option<u32>,
+    /// Heap bytes to request for the single transaction,
+    /// a multiple of 1024 from 32K to 256K.
+    #[clap(long)]
+    pub heap_frame: Option<u32>,
+}
+
+/// Print sysvars from the program, one section per transaction,
+/// then reassemble the sections from the transaction logs
+/// and compare them with the sysvars read over RPC.
+///
+/// With `--single-transaction` the sections are printed by one instruction,
+/// which picks as many sections as fit in the requested compute units,
+/// going by what each section cost when simulated on its own.
 pub fn print_sysvars_via_program(
     config: &crate::util::Config,
     client: &RpcClient,
-    program_keypair: &Keypair,
-) -> Result<()> {
-    let sections = [
-        PrintSysvarsSection::One,
-        PrintSysvarsSection::Two,
-        PrintSysvarsSection::Three,
-    ];
+    opts: PrintSysvarsOpts,
+) -> Result<ProgramSysvarsReport> {
+    let (transactions, compute_budget) = if opts.single_transaction {
+        let (transaction, compute_budget) =
+            print_sysvars_single_transaction(config, client, &opts)?;
+        (vec![transaction], Some(compute_budget))
+    } else {
+        if opts.compute_units.is_some() || opts.heap_frame.is_some() {
+            bail!("--compute-units and --heap-frame require --single-transaction");
+        }
+        (print_sysvars_by_section(config, client)?, None)
+    };
+    let expected_section = if opts.single_transaction {
+        "Dynamic"
+    } else {
+        "One"
+    };
+
+    let program_id = config.program_id().to_string();
+    let messages: Vec<&str> = transactions
+        .iter()
+        .flat_map(|tx| &tx.invocations)
+        .filter(|invocation| invocation.program_id == program_id)
+        .flat_map(|invocation| &invocation.logs)
+        .map(String::as_str)
+        .collect();
+
+    let clock = find_debug_fields(&messages, "clock")
+        .map(|fields| parse_clock(&fields))
+        .transpose()?;
+    let epoch_schedule = find_debug_fields(&messages, "epoch_schedule")
+        .map(|fields| parse_epoch_schedule(&fields))
+        .transpose()?;
+    let rent = find_debug_fields(&messages, "rent")
+        .map(|fields| parse_rent(&fields))
+        .transpose()?;
+    let instruction = find_debug(&messages, "deserialized_instruction_data")
+        .map(parse_decoded_instruction)
+        .transpose()?;
+
+    let rpc = print_sysvars_via_client(client)?;
+    let discrepancies = compare_with_rpc(
+        clock.as_ref(),
+        epoch_schedule.as_ref(),
+        rent.as_ref(),
+        instruction.as_ref(),
+        expected_section,
+        &rpc,
+    );
 
-    for section in sections {
-        let instr =
-            PrintSysvarsInstruction { section }.build_instruction(&program_keypair.pubkey());
+    Ok(ProgramSysvarsReport {
+        transactions,
+        clock,
+        epoch_schedule,
+        rent,
+        instruction,
+        discrepancies,
+        compute_budget,
+    })
+}
+
+fn print_sysvars_by_section(
+    config: &crate::util::Config,
+    client: &RpcClient,
+) -> Result<Vec<TransactionReport>> {
+    let mut transactions = vec![];
+
+    for section in SECTIONS {
+        let instr = PrintSysvarsInstruction { section }.build_instruction(config.program_id());
 
         let blockhash = client.get_latest_blockhash()?;
         let tx = Transaction::new_signed_with_payer(
             &[instr],
-            Some(&config.keypair.pubkey()),
-            &[&config.keypair],
+            Some(&config.keypair().pubkey()),
+            &[config.keypair()],
             blockhash,
         );
 
-        let sig = client.send_and_confirm_transaction(&tx)?;
-        println!("{:?} sig: {}", section, sig);
+        let transaction = util::send_and_confirm(config, client, &tx)?;
+        transactions.push(transaction.with_label(format!("{:?}", section)));
     }
 
-    Ok(())
+    Ok(transactions)
 }
 
-pub fn print_sysvars_via_client(client: &RpcClient) -> Result<()> {
-    println!("--------------------------------------- sysvar client printing ---------------------------------------");
+fn print_sysvars_single_transaction(
+    config: &crate::util::Config,
+    client: &RpcClient,
+    opts: &PrintSysvarsOpts,
+) -> Result<(TransactionReport, ComputeBudgetReport)> {
+    let compute_units = opts.compute_units.unwrap_or(MAX_COMPUTE_UNITS);
+    if compute_units > MAX_COMPUTE_UNITS {
+        bail!("--compute-units can be at most {}", MAX_COMPUTE_UNITS);
+    }
 
-    use solana_sdk::sysvar::{
-        clock, clock::Clock, epoch_schedule, epoch_schedule::EpochSchedule, instructions, rent,
-        rent::Rent, slot_hashes, slot_hashes::SlotHashes, slot_history, slot_history::SlotHistory,
-        stake_history, stake_history::StakeHistory, Sysvar,
-    };
+    let measured = measure_sections(config, client)?;
 
-    let sysvar_program_id = clock::ID;
-    println!("clock::ID: {}", sysvar_program_id);
-    println!("clock::check_id: {}", clock::check_id(&sysvar_program_id));
-    println!("clock::Clock::size_of: {}", Clock::size_of());
+    let mut instrs = vec![request_units_instruction(compute_units)];
 
-    let account = client.get_account(&sysvar_program_id)?;
-    println!("clock account: {:#?}", account);
-    let data: Clock = bincode::deserialize(&account.data)?;
-    println!("clock account data: {:#?}", data);
+    if let Some(heap_frame) = opts.heap_frame {
+        if heap_frame % 1024 != 0 || !(32 * 1024..=256 * 1024).contains(&heap_frame) {
+            bail!("--heap-frame must be a multiple of 1024 from 32K to 256K");
+        }
+        instrs.push(ComputeBudgetInstruction::request_heap_frame(heap_frame));
+    }
 
-    let sysvar_program_id = epoch_schedule::ID;
-    println!("epoch_schedule::ID: {}", sysvar_program_id);
-    println!(
-        "epoch_schedule::check_id: {}",
-        epoch_schedule::check_id(&sysvar_program_id)
+    instrs.push(
+        PrintSysvarsInstruction {
+            section: PrintSysvarsSection::Dynamic {
+                compute_units,
+                base_units: measured.base_units,
+                section_units: measured.section_units,
+            },
+        }
+        .build_instruction(config.program_id()),
     );
-    println!(
-        "epoch_schedule::EpochSchedule::size_of: {}",
-        EpochSchedule::size_of()
+
+    let blockhash = client.get_latest_blockhash()?;
+    let tx = Transaction::new_signed_with_payer(
+        &instrs,
+        Some(&config.keypair().pubkey()),
+        &[config.keypair()],
+        blockhash,
     );
 
-    let account = client.get_account(&sysvar_program_id)?;
-    println!("epoch_schedule account: {:#?}", account);
-    let data: EpochSchedule = bincode::deserialize(&account.data)?;
-    println!("epoch_schedule account data: {:#?}", data);
+    let transaction = util::send_and_confirm(config, client, &tx)?;
 
-    let sysvar_program_id = instructions::ID;
-    println!("instructions::ID: {}", sysvar_program_id);
-    println!(
-        "instructions::check_id: {}",
-        instructions::check_id(&sysvar_program_id)
-    );
+    let program_id = config.program_id().to_string();
+    let invocation = transaction
+        .invocations
+        .iter()
+        .find(|invocation| invocation.program_id == program_id);
 
-    let sysvar_program_id = rent::ID;
-    println!("rent::ID: {}", sysvar_program_id);
-    println!("rent::check_id: {}", rent::check_id(&sysvar_program_id));
-    println!("rent::Rent::size_of: {}", Rent::size_of());
+    let mut sections_printed = vec![];
+    let mut sections_skipped = vec![];
+    for msg in invocation.iter().flat_map(|invocation| &invocation.logs) {
+        if let Some(section) = msg.strip_prefix("section ") {
+            if !section.starts_with("Dynamic") {
+                sections_printed.push(section.to_string());
+            }
+        } else if let Some(rest) = msg.strip_prefix("skipping section ") {
+            let section = rest.split(':').next().unwrap_or(rest);
+            sections_skipped.push(section.to_string());
+        }
+    }
 
-    let account = client.get_account(&sysvar_program_id)?;
-    println!("rent account: {:#?}", account);
-    let data: Rent = bincode::deserialize(&account.data)?;
-    println!("rent account data: {:?}", data);
+    let compute_budget = ComputeBudgetReport {
+        requested_units: compute_units,
+        heap_frame: opts.heap_frame,
+        measured,
+        units_consumed: invocation.and_then(|invocation| invocation.units_consumed),
+        sections_printed,
+        sections_skipped,
+    };
 
-    let sysvar_program_id = slot_hashes::ID;
-    println!("slot_hashes::ID: {}", sysvar_program_id);
-    println!(
-        "slot_hashes::check_id: {}",
-        slot_hashes::check_id(&sysvar_program_id)
-    );
-    println!(
-        "slot_hashes::SlotHashes::size_of: {}",
-        SlotHashes::size_of()
-    );
+    Ok((transaction, compute_budget))
+}
 
-    let account = client.get_account(&sysvar_program_id)?;
-    println!("slot_hashes account: {:#?}", account);
-    let data: SlotHashes = bincode::deserialize(&account.data)?;
-    println!("slot_hashes account data: {:?}", data);
+/// Simulate printing each section in its own transaction,
+/// and measure the units it spends from the checkpoints it logs.
+///
+/// The base is what the program spends up to its `overhead` checkpoint,
+/// before printing anything, and each section is what it spends after that.
+fn measure_sections(config: &crate::util::Config, client: &RpcClient) -> Result<SectionUnits> {
+    let simulate = config.simulate.unwrap_or(SimulateConfig {
+        sig_verify: false,
+        replace_blockhash: true,
+    });
+    let program_id = config.program_id().to_string();
 
-    let sysvar_program_id = slot_history::ID;
-    println!("slot_history::ID: {}", sysvar_program_id);
-    println!(
-        "slot_history::check_id: {}",
-        slot_history::check_id(&sysvar_program_id)
-    );
-    println!(
-        "slot_history::SlotHistory::size_of: {}",
-        SlotHistory::size_of()
-    );
+    let mut base_units = 0;
+    let mut section_units = [0; 3];
 
-    let account = client.get_account(&sysvar_program_id)?;
-    println!("slot_history account: {:#?}", account);
-    let data: SlotHistory = bincode::deserialize(&account.data)?;
-    println!("slot_history account data: {:?}", data);
+    for (section, units) in SECTIONS.into_iter().zip(&mut section_units) {
+        info!("measuring section {:?}", section);
 
-    let sysvar_program_id = stake_history::ID;
-    println!("stake_history::ID: {}", sysvar_program_id);
-    println!(
-        "stake_history::check_id: {}",
-        stake_history::check_id(&sysvar_program_id)
-    );
-    println!(
-        "stake_history::StakeHistory::size_of: {}",
-        StakeHistory::size_of()
-    );
-    let account = client.get_account(&sysvar_program_id)?;
-    println!("stake_history account: {:#?}", account);
+        let instr = PrintSysvarsInstruction { section }.build_instruction(config.program_id());
+        let blockhash = client.get_latest_blockhash()?;
+        let tx = Transaction::new_signed_with_payer(
+            &[instr],
+            Some(&config.keypair().pubkey()),
+            &[config.keypair()],
+            blockhash,
+        );
+
+        let transaction = util::simulate_transaction(client, &tx, simulate)?;
+        let invocation = transaction
+            .invocations
+            .iter()
+            .find(|invocation| invocation.program_id == program_id && invocation.succeeded())
+            .ok_or_else(|| anyhow!("section {:?} failed when simulated", section))?;
+
+        let checkpoints = logs::checkpoints(invocation);
+        let overhead = checkpoints
+            .iter()
+            .find(|checkpoint| checkpoint.label == "overhead")
+            .map(|checkpoint| checkpoint.remaining);
+        let last = checkpoints.last().map(|checkpoint| checkpoint.remaining);
+        let (budget, overhead, last) = match (invocation.units_budget, overhead, last) {
+            (Some(budget), Some(overhead), Some(last)) => (budget, overhead, last),
+            _ => bail!("section {:?} logged no checkpoints", section),
+        };
+
+        base_units = base_units.max(budget.saturating_sub(overhead));
+        *units = with_headroom(overhead.saturating_sub(last));
+    }
+
+    Ok(SectionUnits {
+        base_units: with_headroom(base_units),
+        section_units,
+    })
+}
+
+fn with_headroom(units: u64) -> u32 {
+    let units = units + units * MEASUREMENT_HEADROOM_PERCENT / 100;
+    u32::try_from(units).unwrap_or(u32::MAX)
+}
+
+/// The deprecated `ComputeBudgetInstruction::RequestUnits { units, additional_fee }`,
+/// which is what 1.10 validators honor for raising the compute limit.
+///
+/// It's built by hand since its constructor is named differently across SDK versions.
+fn request_units_instruction(units: u32) -> Instruction {
+    let mut data = vec![0];
+    data.extend(units.to_le_bytes());
+    data.extend(0u32.to_le_bytes());
+
+    Instruction::new_with_bytes(compute_budget::id(), &data, vec![])
+}
+
+/// The sysvars printed by the program's three sections,
+/// parsed back out of their `Debug` output.
+///
+/// A sysvar is missing if its section's logs didn't contain it,
+/// e.g. because the transaction failed or its logs were truncated.
+#[derive(Serialize, Debug)]
+pub struct ProgramSysvarsReport {
+    pub transactions: Vec<TransactionReport>,
+    pub clock: Option<Clock>,
+    pub epoch_schedule: Option<EpochSchedule>,
+    pub rent: Option<Rent>,
+    /// The program's own instruction, decoded from the instructions sysvar.
+    pub instruction: Option<DecodedInstruction>,
+    /// Fields where the program and RPC disagree.
+    pub discrepancies: Vec<Discrepancy>,
+    /// Set when printed by a single transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compute_budget: Option<ComputeBudgetReport>,
+}
+
+/// What a single-transaction print requested, and what it used.
+#[derive(Serialize, Debug)]
+pub struct ComputeBudgetReport {
+    pub requested_units: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heap_frame: Option<u32>,
+    /// The costs the program chose sections by.
+    pub measured: SectionUnits,
+    /// Units consumed by the program instruction.
+    pub units_consumed: Option<u64>,
+    pub sections_printed: Vec<String>,
+    pub sections_skipped: Vec<String>,
+}
+
+/// The units the program spends before printing,
+/// and in sections `One`, `Two` and `Three`, with headroom.
+#[derive(Serialize, Debug)]
+pub struct SectionUnits {
+    pub base_units: u32,
+    pub section_units: [u32; 3],
+}
+
+#[derive(Serialize, Debug)]
+pub struct DecodedInstruction {
+    /// The `CustomInstruction` variant.
+    pub variant: String,
+    pub section: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Discrepancy {
+    pub sysvar: &'static str,
+    pub field: &'static str,
+    pub program: String,
+    pub rpc: String,
+}
+
+impl Report for ProgramSysvarsReport {
+    fn print_text(&self) {
+        for transaction in &self.transactions {
+            transaction.print_text();
+        }
+
+        println!("--------------------------------------- sysvar program report ---------------------------------------");
+
+        print_parsed("clock", &self.clock);
+        print_parsed("epoch_schedule", &self.epoch_schedule);
+        print_parsed("rent", &self.rent);
+        print_parsed("instruction", &self.instruction);
+
+        if self.discrepancies.is_empty() {
+            println!("program and RPC agree");
+        }
+        for discrepancy in &self.discrepancies {
+            println!(
+                "discrepancy: {}.{}: program {}, RPC {}",
+                discrepancy.sysvar, discrepancy.field, discrepancy.program, discrepancy.rpc
+            );
+        }
+
+        if let Some(compute_budget) = &self.compute_budget {
+            println!("requested units: {}", compute_budget.requested_units);
+            if let Some(heap_frame) = compute_budget.heap_frame {
+                println!("heap frame: {}", heap_frame);
+            }
+            println!(
+                "measured units: base {}, sections {:?}",
+                compute_budget.measured.base_units, compute_budget.measured.section_units
+            );
+            match compute_budget.units_consumed {
+                Some(units) => println!("units consumed: {}", units),
+                None => println!("units consumed: unknown"),
+            }
+            println!("sections printed: {:?}", compute_budget.sections_printed);
+            println!("sections skipped: {:?}", compute_budget.sections_skipped);
+        }
+    }
+}
+
+fn print_parsed<T: Debug>(name: &str, value: &Option<T>) {
+    match value {
+        Some(value) => println!("{}: {:#?}", name, value),
+        None => println!("{}: not found in logs", name),
+    }
+}
+
+/// Find the log message `"{name}: {value:#?}"` and return the `Debug` text.
+fn find_debug<'a>(messages: &[&'a str], name: &str) -> Option<&'a str> {
+    let prefix = format!("{}: ", name);
+    messages.iter().find_map(|msg| msg.strip_prefix(&prefix))
+}
+
+/// Find a pretty-printed struct and collect its `field: value,` lines.
+///
+/// Nested fields are collected too, which is enough for the flat sysvar structs.
+fn find_debug_fields<'a>(messages: &[&'a str], name: &str) -> Option<HashMap<&'a str, &'a str>> {
+    find_debug(messages, name).map(debug_fields)
+}
+
+fn debug_fields(debug: &str) -> HashMap<&str, &str> {
+    debug
+        .lines()
+        .filter_map(|line| {
+            let (field, value) = line.trim().split_once(": ")?;
+            Some((field, value.trim_end_matches(',')))
+        })
+        .collect()
+}
+
+fn field<T>(fields: &HashMap<&str, &str>, sysvar: &str, name: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let value = fields
+        .get(name)
+        .ok_or_else(|| anyhow!("{} log is missing {}", sysvar, name))?;
+    value
+        .parse()
+        .with_context(|| format!("unable to parse {}.{} from {:?}", sysvar, name, value))
+}
+
+fn parse_clock(fields: &HashMap<&str, &str>) -> Result<Clock> {
+    Ok(Clock {
+        slot: field(fields, "clock", "slot")?,
+        epoch_start_timestamp: field(fields, "clock", "epoch_start_timestamp")?,
+        epoch: field(fields, "clock", "epoch")?,
+        leader_schedule_epoch: field(fields, "clock", "leader_schedule_epoch")?,
+        unix_timestamp: field(fields, "clock", "unix_timestamp")?,
+    })
+}
+
+fn parse_epoch_schedule(fields: &HashMap<&str, &str>) -> Result<EpochSchedule> {
+    Ok(EpochSchedule {
+        slots_per_epoch: field(fields, "epoch_schedule", "slots_per_epoch")?,
+        leader_schedule_slot_offset: field(
+            fields,
+            "epoch_schedule",
+            "leader_schedule_slot_offset",
+        )?,
+        warmup: field(fields, "epoch_schedule", "warmup")?,
+        first_normal_epoch: field(fields, "epoch_schedule", "first_normal_epoch")?,
+        first_normal_slot: field(fields, "epoch_schedule", "first_normal_slot")?,
+    })
+}
+
+fn parse_rent(fields: &HashMap<&str, &str>) -> Result<Rent> {
+    Ok(Rent {
+        lamports_per_byte_year: field(fields, "rent", "lamports_per_byte_year")?,
+        exemption_threshold: field(fields, "rent", "exemption_threshold")?,
+        burn_percent: field(fields, "rent", "burn_percent")?,
+    })
+}
+
+/// Parse e.g. `PrintSysvars(PrintSysvarsInstruction { section: One })`.
+fn parse_decoded_instruction(debug: &str) -> Result<DecodedInstruction> {
+    let variant = debug
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .filter(|variant| !variant.is_empty())
+        .ok_or_else(|| anyhow!("unable to parse instruction from {:?}", debug))?;
+    // Only the variant name, e.g. `Dynamic` from `Dynamic {`.
+    let section = debug_fields(debug)
+        .get("section")
+        .and_then(|s| s.split(|c: char| !c.is_alphanumeric()).next())
+        .map(str::to_string);
+
+    Ok(DecodedInstruction {
+        variant: variant.to_string(),
+        section,
+    })
+}
+
+/// Compare the program's view of the sysvars with RPC's.
+///
+/// RPC is read after the transactions confirm,
+/// so the clock is only a discrepancy if the program saw a later value than RPC.
+fn compare_with_rpc(
+    clock: Option<&Clock>,
+    epoch_schedule: Option<&EpochSchedule>,
+    rent: Option<&Rent>,
+    instruction: Option<&DecodedInstruction>,
+    expected_section: &str,
+    rpc: &SysvarsReport,
+) -> Vec<Discrepancy> {
+    let mut discrepancies = vec![];
 
+    let mut check = |sysvar, field, program: String, rpc: String, agree: bool| {
+        if !agree {
+            discrepancies.push(Discrepancy {
+                sysvar,
+                field,
+                program,
+                rpc,
+            });
+        }
+    };
+
+    macro_rules! exact {
+        ($sysvar:expr, $program:expr, $rpc:expr, $field:ident) => {
+            check(
+                $sysvar,
+                stringify!($field),
+                $program.$field.to_string(),
+                $rpc.$field.to_string(),
+                $program.$field == $rpc.$field,
+            )
+        };
+    }
+
+    macro_rules! not_after {
+        ($sysvar:expr, $program:expr, $rpc:expr, $field:ident) => {
+            check(
+                $sysvar,
+                stringify!($field),
+                $program.$field.to_string(),
+                $rpc.$field.to_string(),
+                $program.$field <= $rpc.$field,
+            )
+        };
+    }
+
+    if let Some(clock) = clock {
+        let rpc = &rpc.clock.data;
+        not_after!("clock", clock, rpc, slot);
+        not_after!("clock", clock, rpc, epoch_start_timestamp);
+        not_after!("clock", clock, rpc, epoch);
+        not_after!("clock", clock, rpc, leader_schedule_epoch);
+        not_after!("clock", clock, rpc, unix_timestamp);
+    }
+
+    if let Some(epoch_schedule) = epoch_schedule {
+        let rpc = &rpc.epoch_schedule.data;
+        exact!("epoch_schedule", epoch_schedule, rpc, slots_per_epoch);
+        exact!(
+            "epoch_schedule",
+            epoch_schedule,
+            rpc,
+            leader_schedule_slot_offset
+        );
+        exact!("epoch_schedule", epoch_schedule, rpc, warmup);
+        exact!("epoch_schedule", epoch_schedule, rpc, first_normal_epoch);
+        exact!("epoch_schedule", epoch_schedule, rpc, first_normal_slot);
+    }
+
+    if let Some(rent) = rent {
+        let rpc = &rpc.rent.data;
+        exact!("rent", rent, rpc, lamports_per_byte_year);
+        exact!("rent", rent, rpc, exemption_threshold);
+        exact!("rent", rent, rpc, burn_percent);
+    }
+
+    // The instruction has no RPC counterpart,
+    // but should match the instruction the client sent.
+    if let Some(instruction) = instruction {
+        let expected = expected_section.to_string();
+        let section = instruction.section.clone().unwrap_or_default();
+        let agree = section == expected;
+        check("instructions", "section", section, expected, agree);
+    }
+
+    discrepancies
+}
+
+/// Every sysvar, as read over RPC.
+#[derive(Serialize, Debug)]
+pub struct SysvarsReport {
+    pub clock: SysvarReport<Clock>,
+    pub epoch_schedule: SysvarReport<EpochSchedule>,
+    /// The instructions sysvar has no account outside of a transaction.
+    pub instructions: SysvarId,
+    pub rent: SysvarReport<Rent>,
+    pub slot_hashes: SysvarReport<Vec<SlotHashEntry>>,
+    pub slot_history: SysvarReport<SlotHistorySummary>,
+    pub stake_history: SysvarReport<Vec<StakeHistoryEntry>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SysvarId {
+    #[serde(serialize_with = "output::display")]
+    pub id: Pubkey,
+    pub check_id: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SysvarReport<T> {
+    #[serde(flatten)]
+    pub id: SysvarId,
+    pub size_of: usize,
+    pub account: AccountSummary,
+    pub data: T,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AccountSummary {
+    pub lamports: u64,
+    pub data_len: usize,
+    #[serde(serialize_with = "output::display")]
+    pub owner: Pubkey,
+    pub executable: bool,
+    pub rent_epoch: Epoch,
+}
+
+impl From<&Account> for AccountSummary {
+    fn from(account: &Account) -> AccountSummary {
+        AccountSummary {
+            lamports: account.lamports,
+            data_len: account.data.len(),
+            owner: account.owner,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct SlotHashEntry {
+    pub slot: Slot,
+    pub hash: String,
+}
+
+/// `SlotHistory` is a bitvector of a million slots,
+/// which is too much to print.
+#[derive(Serialize, Debug)]
+pub struct SlotHistorySummary {
+    pub next_slot: Slot,
+    pub oldest: Slot,
+    pub newest: Slot,
+}
+
+#[derive(Serialize, Debug)]
+pub struct StakeHistoryEntry {
+    pub epoch: Epoch,
+    pub effective: u64,
+    pub activating: u64,
+    pub deactivating: u64,
+}
+
+impl Report for SysvarsReport {
+    fn print_text(&self) {
+        println!("--------------------------------------- sysvar client printing ---------------------------------------");
+
+        print_sysvar("clock", "Clock", &self.clock);
+        print_sysvar("epoch_schedule", "EpochSchedule", &self.epoch_schedule);
+
+        println!("instructions::ID: {}", self.instructions.id);
+        println!("instructions::check_id: {}", self.instructions.check_id);
+
+        print_sysvar("rent", "Rent", &self.rent);
+        print_sysvar("slot_hashes", "SlotHashes", &self.slot_hashes);
+        print_sysvar("slot_history", "SlotHistory", &self.slot_history);
+        print_sysvar("stake_history", "StakeHistory", &self.stake_history);
+    }
+}
+
+fn print_sysvar<T: Debug>(name: &str, type_name: &str, report: &SysvarReport<T>) {
+    println!("{}::ID: {}", name, report.id.id);
+    println!("{}::check_id: {}", name, report.id.check_id);
+    println!("{}::{}::size_of: {}", name, type_name, report.size_of);
+    println!("{} account: {:#?}", name, report.account);
+    println!("{} account data: {:#?}", name, report.data);
+}
+
+pub fn print_sysvars_via_client(client: &RpcClient) -> Result<SysvarsReport> {
+    use solana_sdk::sysvar::{
+        clock, epoch_schedule, instructions, rent, slot_hashes, slot_history, stake_history, Sysvar,
+    };
+
+    let account = client.get_account(&clock::ID)?;
+    let data: Clock = bincode::deserialize(&account.data)?;
+    let clock = SysvarReport {
+        id: SysvarId {
+            id: clock::ID,
+            check_id: clock::check_id(&clock::ID),
+        },
+        size_of: Clock::size_of(),
+        account: AccountSummary::from(&account),
+        data,
+    };
+
+    let account = client.get_account(&epoch_schedule::ID)?;
+    let data: EpochSchedule = bincode::deserialize(&account.data)?;
+    let epoch_schedule = SysvarReport {
+        id: SysvarId {
+            id: epoch_schedule::ID,
+            check_id: epoch_schedule::check_id(&epoch_schedule::ID),
+        },
+        size_of: EpochSchedule::size_of(),
+        account: AccountSummary::from(&account),
+        data,
+    };
+
+    let instructions = SysvarId {
+        id: instructions::ID,
+        check_id: instructions::check_id(&instructions::ID),
+    };
+
+    let account = client.get_account(&rent::ID)?;
+    let data: Rent = bincode::deserialize(&account.data)?;
+    let rent = SysvarReport {
+        id: SysvarId {
+            id: rent::ID,
+            check_id: rent::check_id(&rent::ID),
+        },
+        size_of: Rent::size_of(),
+        account: AccountSummary::from(&account),
+        data,
+    };
+
+    let account = client.get_account(&slot_hashes::ID)?;
+    let data: SlotHashes = bincode::deserialize(&account.data)?;
+    let slot_hashes = SysvarReport {
+        id: SysvarId {
+            id: slot_hashes::ID,
+            check_id: slot_hashes::check_id(&slot_hashes::ID),
+        },
+        size_of: SlotHashes::size_of(),
+        account: AccountSummary::from(&account),
+        data: data
+            .iter()
+            .map(|(slot, hash)| SlotHashEntry {
+                slot: *slot,
+                hash: hash.to_string(),
+            })
+            .collect(),
+    };
+
+    let account = client.get_account(&slot_history::ID)?;
+    let data: SlotHistory = bincode::deserialize(&account.data)?;
+    let slot_history = SysvarReport {
+        id: SysvarId {
+            id: slot_history::ID,
+            check_id: slot_history::check_id(&slot_history::ID),
+        },
+        size_of: SlotHistory::size_of(),
+        account: AccountSummary::from(&account),
+        data: SlotHistorySummary {
+            next_slot: data.next_slot,
+            oldest: data.oldest(),
+            newest: data.newest(),
+        },
+    };
+
+    let account = client.get_account(&stake_history::ID)?;
     let data: StakeHistory = bincode::deserialize(&account.data)?;
-    println!("stake_history account data: {:#?}", data);
+    let stake_history = SysvarReport {
+        id: SysvarId {
+            id: stake_history::ID,
+            check_id: stake_history::check_id(&stake_history::ID),
+        },
+        size_of: StakeHistory::size_of(),
+        account: AccountSummary::from(&account),
+        data: data
+            .iter()
+            .map(|(epoch, entry)| StakeHistoryEntry {
+                epoch: *epoch,
+                effective: entry.effective,
+                activating: entry.activating,
+                deactivating: entry.deactivating,
+            })
+            .collect(),
+    };
+
+    Ok(SysvarsReport {
+        clock,
+        epoch_schedule,
+        instructions,
+        rent,
+        slot_hashes,
+        slot_history,
+        stake_history,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Messages logged by section one, as returned in the transaction logs.
+    const SECTION_ONE: &[&str] = &[
+        "sysvar program printing",
+        "section One",
+        "checkpoint: start",
+        "clock: Clock {
+    slot: 44745,
+    epoch_start_timestamp: 1645332022,
+    epoch: 0,
+    leader_schedule_epoch: 1,
+    unix_timestamp: 1645364238,
+}",
+        "checkpoint: clock",
+        "epoch_schedule: EpochSchedule {
+    slots_per_epoch: 432000,
+    leader_schedule_slot_offset: 432000,
+    warmup: false,
+    first_normal_epoch: 0,
+    first_normal_slot: 0,
+}",
+        "checkpoint: epoch_schedule",
+        "deserialized_instruction_data: PrintSysvars(
+    PrintSysvarsInstruction {
+        section: One,
+    },
+)",
+        "checkpoint: instructions",
+    ];
+
+    /// Messages logged by section two.
+    const SECTION_TWO: &[&str] = &[
+        "sysvar program printing",
+        "section Two",
+        "rent: Rent {
+    lamports_per_byte_year: 3480,
+    exemption_threshold: 2.0,
+    burn_percent: 50,
+}",
+        "checkpoint: rent",
+        "slot_hashes: AccountInfo {
+    key: SysvarS1otHashes111111111111111111111111111,
+    owner: Sysvar1111111111111111111111111111111111111,
+    is_signer: false,
+    is_writable: false,
+    executable: false,
+    rent_epoch: 0,
+    lamports: 143487360,
+    data.len: 20488,
+    data: 0002000000000000c8ae000000000000cf31df1ad446849db940f840c55832651ac6234f3617edb3883cfc6df890a790c7ae000000000000286bf9e6960ac14a,
+    ..
+}",
+        "checkpoint: slot_hashes",
+    ];
+
+    #[test]
+    fn parse_clock_from_log() {
+        let fields = find_debug_fields(SECTION_ONE, "clock").unwrap();
+        let clock = parse_clock(&fields).unwrap();
+
+        assert_eq!(
+            clock,
+            Clock {
+                slot: 44745,
+                epoch_start_timestamp: 1645332022,
+                epoch: 0,
+                leader_schedule_epoch: 1,
+                unix_timestamp: 1645364238,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_epoch_schedule_from_log() {
+        let fields = find_debug_fields(SECTION_ONE, "epoch_schedule").unwrap();
+        let epoch_schedule = parse_epoch_schedule(&fields).unwrap();
 
-    Ok(())
+        assert_eq!(
+            epoch_schedule,
+            EpochSchedule {
+                slots_per_epoch: 432000,
+                leader_schedule_slot_offset: 432000,
+                warmup: false,
+                first_normal_epoch: 0,
+                first_normal_slot: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_rent_from_log() {
+        let fields = find_debug_fields(SECTION_TWO, "rent").unwrap();
+        let rent = parse_rent(&fields).unwrap();
+
+        assert_eq!(
+            rent,
+            Rent {
+                lamports_per_byte_year: 3480,
+                exemption_threshold: 2.0,
+                burn_percent: 50,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_instruction_from_log() {
+        let debug = find_debug(SECTION_ONE, "deserialized_instruction_data").unwrap();
+        let instruction = parse_decoded_instruction(debug).unwrap();
+        assert_eq!(instruction.variant, "PrintSysvars");
+        assert_eq!(instruction.section.as_deref(), Some("One"));
+
+        let debug = "PrintSysvars(
+    PrintSysvarsInstruction {
+        section: Dynamic {
+            compute_units: 1400000,
+            base_units: 8400,
+            section_units: [
+                126000,
+                99750,
+                105000,
+            ],
+        },
+    },
+)";
+        let instruction = parse_decoded_instruction(debug).unwrap();
+        assert_eq!(instruction.variant, "PrintSysvars");
+        assert_eq!(instruction.section.as_deref(), Some("Dynamic"));
+    }
+
+    #[test]
+    fn sysvar_missing_from_log() {
+        assert!(find_debug_fields(SECTION_TWO, "clock").is_none());
+        assert!(find_debug_fields(SECTION_ONE, "rent").is_none());
+        assert!(find_debug(SECTION_TWO, "deserialized_instruction_data").is_none());
+    }
+
+    #[test]
+    fn parse_malformed_log() {
+        // A non-numeric field, and the rest cut off.
+        let messages = &["clock: Clock {
+    slot: 44745,
+    epoch_start_timestamp: -,
+    epoch: 0,"];
+        let fields = find_debug_fields(messages, "clock").unwrap();
+        let err = parse_clock(&fields).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"unable to parse clock.epoch_start_timestamp from "-""#
+        );
+
+        let mut fields = find_debug_fields(messages, "clock").unwrap();
+        fields.remove("epoch_start_timestamp");
+        let err = parse_clock(&fields).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "clock log is missing epoch_start_timestamp"
+        );
+
+        assert!(parse_decoded_instruction("(").is_err());
+    }
 }
@@ -1,52 +1,299 @@
+use crate::logs;
+use crate::output::{AccountChange, OutputFormat, SimulationReport, TransactionReport};
 use anyhow::{anyhow, bail, Context, Result};
 use log::info;
-use solana_client::rpc_client::RpcClient;
+use serde::Deserialize;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{
+        RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig, RpcTransactionConfig,
+    },
+};
 use solana_sdk::{
+    account::Account,
     commitment_config::CommitmentConfig,
-    signature::{read_keypair_file, Keypair, Signer},
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signature, Signer},
+    transaction::{Transaction, TransactionError},
 };
+use solana_transaction_status::UiTransactionEncoding;
+use std::fs::File;
+use std::path::Path;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
 
 static DEPLOY_PATH: &str = "target/deploy";
 static PROGRAM_KEYPAIR_PATH: &str = "program-keypair.json";
+static CALLER_PROGRAM_KEYPAIR_PATH: &str = "caller_program-keypair.json";
 
 pub struct Config {
     pub json_rpc_url: String,
     pub websocket_url: String,
-    pub keypair: Keypair,
+    pub keypair_path: String,
+    pub commitment: CommitmentConfig,
+    pub output: OutputFormat,
+    pub simulate: Option<SimulateConfig>,
+    keypair: Option<Keypair>,
+    program_id: Option<Pubkey>,
 }
 
-pub fn load_config(rpc_url: Option<String>) -> Result<Config> {
-    let config_file = solana_cli_config::CONFIG_FILE
+impl Config {
+    /// The payer and signer of demo transactions.
+    ///
+    /// Only available to commands that require a wallet.
+    pub fn keypair(&self) -> &Keypair {
+        self.keypair
+            .as_ref()
+            .expect("command does not declare that it requires a wallet")
+    }
+
+    /// The id of the deployed `program`.
+    ///
+    /// Only available to commands that require the program.
+    pub fn program_id(&self) -> &Pubkey {
+        self.program_id
+            .as_ref()
+            .expect("command does not declare that it requires the program")
+    }
+}
+
+/// What a command needs before it can run.
+#[derive(Copy, Clone, Debug)]
+pub struct Requirements {
+    /// A connection to the RPC endpoint.
+    pub rpc: bool,
+    /// A keypair to pay for and sign transactions.
+    pub wallet: bool,
+    /// A deployed instance of `program`.
+    pub program: bool,
+}
+
+impl Requirements {
+    /// Only talks to the RPC endpoint.
+    pub const READ_ONLY: Requirements = Requirements {
+        rpc: true,
+        wallet: false,
+        program: false,
+    };
+    /// Sends transactions, but not to our program.
+    pub const WALLET: Requirements = Requirements {
+        rpc: true,
+        wallet: true,
+        program: false,
+    };
+    /// Sends transactions to our program.
+    pub const WALLET_AND_PROGRAM: Requirements = Requirements {
+        rpc: true,
+        wallet: true,
+        program: true,
+    };
+    /// Reads our program's accounts, without sending transactions.
+    pub const PROGRAM: Requirements = Requirements {
+        rpc: true,
+        wallet: false,
+        program: true,
+    };
+    /// Signs transactions without touching the network.
+    pub const OFFLINE_WALLET: Requirements = Requirements {
+        rpc: false,
+        wallet: true,
+        program: false,
+    };
+}
+
+/// How to simulate transactions instead of sending them.
+#[derive(Copy, Clone, Debug)]
+pub struct SimulateConfig {
+    pub sig_verify: bool,
+    pub replace_blockhash: bool,
+}
+
+/// Global command line options that override the config file.
+#[derive(clap::Args)]
+pub struct ConfigOpts {
+    /// JSON RPC URL or moniker (mainnet-beta, testnet, devnet, localhost).
+    #[clap(long, global = true)]
+    pub url: Option<String>,
+    /// WebSocket URL, computed from the JSON RPC URL by default.
+    #[clap(long, global = true)]
+    pub ws_url: Option<String>,
+    /// Path to the payer keypair file.
+    #[clap(long, global = true)]
+    pub keypair: Option<String>,
+    /// The deployed program to use, instead of the one in `target/deploy`.
+    #[clap(long, global = true)]
+    pub program_id: Option<Pubkey>,
+    /// Commitment level: processed, confirmed or finalized.
+    #[clap(long, global = true)]
+    pub commitment: Option<String>,
+    /// Config file, defaults to the Solana CLI config file.
+    #[clap(long, global = true)]
+    pub config: Option<String>,
+    /// Print a human-readable report, or a single JSON document.
+    #[clap(long, global = true, arg_enum, default_value = "text")]
+    pub output: OutputFormat,
+    /// Simulate transactions instead of sending them.
+    /// No fees are charged, but the wallet must exist with enough lamports for them.
+    #[clap(long, global = true)]
+    pub simulate: bool,
+    /// Verify signatures when simulating.
+    /// The wallet must be funded either way.
+    #[clap(long, global = true, requires = "simulate")]
+    pub sig_verify: bool,
+    /// Replace the blockhash with the most recent one when simulating.
+    #[clap(long, global = true, requires = "simulate")]
+    pub replace_blockhash: bool,
+}
+
+/// The `client` section of the config file.
+///
+/// This sits beside the Solana CLI settings in the same YAML file,
+/// and its values take precedence over them:
+///
+/// ```yaml
+/// json_rpc_url: "http://localhost:8899"
+/// keypair_path: /home/me/.config/solana/id.json
+/// client:
+///   program_id: AYn3tq1XGucC9EtEnmAHgogPRCUJdB4arFRXszqxxubu
+///   commitment: finalized
+/// ```
+///
+/// Note that `solana config set` rewrites the Solana CLI config file
+/// and drops this section, so it may be better kept in its own file
+/// passed with `--config`.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ClientConfigSection {
+    json_rpc_url: Option<String>,
+    websocket_url: Option<String>,
+    keypair_path: Option<String>,
+    program_id: Option<String>,
+    commitment: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ClientConfigFile {
+    client: ClientConfigSection,
+}
+
+/// Load the config without touching the wallet or the program.
+///
+/// Command line options take precedence over the `client` section of the config file,
+/// which takes precedence over the Solana CLI settings.
+/// The config file is optional,
+/// so read-only commands can run on machines without one.
+pub fn load_config(opts: ConfigOpts) -> Result<Config> {
+    let config_file = opts
+        .config
         .as_ref()
-        .ok_or_else(|| anyhow!("config file path"))?;
-    let cli_config = solana_cli_config::Config::load(config_file)?;
-    let json_rpc_url = rpc_url
-        .clone()
-        .map(|url| solana_clap_utils::input_validators::normalize_to_url_if_moniker(url))
-        .unwrap_or(cli_config.json_rpc_url.clone());
-    let websocket_url = rpc_url
-        .map(|url| solana_clap_utils::input_validators::normalize_to_url_if_moniker(url))
-        .map(|url| solana_cli_config::Config::compute_websocket_url(&url))
-        .unwrap_or(cli_config.websocket_url);
-    let websocket_url = if !websocket_url.is_empty() {
-        websocket_url
-    } else {
-        solana_cli_config::Config::compute_websocket_url(&cli_config.json_rpc_url)
+        .or_else(|| solana_cli_config::CONFIG_FILE.as_ref());
+    let (cli_config, section) = match config_file {
+        Some(config_file) if Path::new(config_file).exists() => {
+            info!("loading config from {}", config_file);
+            let cli_config = solana_cli_config::Config::load(config_file)?;
+            let file: ClientConfigFile = serde_yaml::from_reader(File::open(config_file)?)
+                .with_context(|| format!("unable to parse client section of {}", config_file))?;
+            (cli_config, file.client)
+        }
+        Some(config_file) if opts.config.is_some() => {
+            bail!("config file {} does not exist", config_file);
+        }
+        _ => {
+            info!("no config file, using defaults");
+            (
+                solana_cli_config::Config::default(),
+                ClientConfigSection::default(),
+            )
+        }
     };
 
-    let keypair = read_keypair_file(&cli_config.keypair_path).map_err(|e| anyhow!("{}", e))?;
+    let json_rpc_url_overridden = opts.url.is_some() || section.json_rpc_url.is_some();
+    let json_rpc_url = opts
+        .url
+        .or(section.json_rpc_url)
+        .map(|url| solana_clap_utils::input_validators::normalize_to_url_if_moniker(url))
+        .unwrap_or(cli_config.json_rpc_url);
+    let cli_websocket_url =
+        Some(cli_config.websocket_url).filter(|url| !url.is_empty() && !json_rpc_url_overridden);
+    let websocket_url = opts
+        .ws_url
+        .or(section.websocket_url)
+        .or(cli_websocket_url)
+        .unwrap_or_else(|| solana_cli_config::Config::compute_websocket_url(&json_rpc_url));
+
+    let keypair_path = opts
+        .keypair
+        .or(section.keypair_path)
+        .unwrap_or(cli_config.keypair_path);
+
+    let commitment = opts
+        .commitment
+        .or(section.commitment)
+        .unwrap_or(cli_config.commitment);
+    let commitment = CommitmentConfig::from_str(&commitment)
+        .with_context(|| format!("invalid commitment {:?}", commitment))?;
+
+    let program_id = match (opts.program_id, section.program_id) {
+        (Some(program_id), _) => Some(program_id),
+        (None, Some(program_id)) => Some(
+            Pubkey::from_str(&program_id)
+                .with_context(|| format!("invalid program id {:?}", program_id))?,
+        ),
+        (None, None) => None,
+    };
 
     Ok(Config {
         json_rpc_url,
         websocket_url,
-        keypair,
+        keypair_path,
+        commitment,
+        output: opts.output,
+        simulate: opts.simulate.then(|| SimulateConfig {
+            sig_verify: opts.sig_verify,
+            replace_blockhash: opts.replace_blockhash,
+        }),
+        keypair: None,
+        program_id,
     })
 }
 
+/// Load the wallet and locate the program, if the command needs them.
+pub fn load_requirements(
+    config: &mut Config,
+    client: &RpcClient,
+    requirements: Requirements,
+) -> Result<()> {
+    if requirements.wallet {
+        info!("loading keypair from {}", config.keypair_path);
+
+        let keypair = read_keypair_file(&config.keypair_path)
+            .map_err(|e| anyhow!("{}", e))
+            .with_context(|| format!("unable to load keypair from {}", config.keypair_path))?;
+
+        config.keypair = Some(keypair);
+    }
+
+    if requirements.program {
+        config.program_id = Some(get_program_id(client, config.program_id)?);
+    }
+
+    Ok(())
+}
+
+/// A client for commands that don't talk to the network.
+///
+/// Nothing is sent until a method is called, so this works without a node.
+pub fn offline_client(config: &Config) -> RpcClient {
+    RpcClient::new_with_commitment(config.json_rpc_url.clone(), config.commitment)
+}
+
 pub fn connect(config: &Config) -> Result<RpcClient> {
     info!("connecting to solana node at {}", config.json_rpc_url);
-    let client =
-        RpcClient::new_with_commitment(config.json_rpc_url.clone(), CommitmentConfig::confirmed());
+    let client = RpcClient::new_with_commitment(config.json_rpc_url.clone(), config.commitment);
 
     let version = client.get_version()?;
     info!("RPC version: {:?}", version);
@@ -54,23 +301,196 @@ pub fn connect(config: &Config) -> Result<RpcClient> {
     Ok(client)
 }
 
-pub fn get_program_keypair(client: &RpcClient) -> Result<Keypair> {
-    let manifest_dir = env!("CARGO_MANIFEST_DIR");
-    let deploy_path = format!("{}/../../{}", manifest_dir, DEPLOY_PATH);
-    let program_keypair_path = format!("{}/{}", deploy_path, PROGRAM_KEYPAIR_PATH);
+/// Send a transaction, wait for it to confirm,
+/// then fetch its logs and parse them into program invocations.
+///
+/// Every demo sends its transactions through here.
+/// With `--simulate` the transaction is simulated instead.
+pub fn send_and_confirm(
+    config: &Config,
+    client: &RpcClient,
+    tx: &Transaction,
+) -> Result<TransactionReport> {
+    if let Some(simulate) = config.simulate {
+        return simulate_transaction(client, tx, simulate);
+    }
 
-    info!("loading program keypair from {}", program_keypair_path);
+    let sig = client.send_and_confirm_transaction(tx)?;
 
-    let program_keypair = read_keypair_file(&program_keypair_path)
-        .map_err(|e| anyhow!("{}", e))
-        .context("unable to load program keypair")?;
+    let logs = get_transaction_logs(config, client, &sig)?;
+    let mut invocations = logs::parse_logs(&logs);
+    logs::assign_instruction_indexes(
+        &mut invocations,
+        &tx.message.account_keys,
+        &tx.message.instructions,
+    );
 
-    let program_id = program_keypair.pubkey();
+    Ok(TransactionReport::new(sig, logs, invocations))
+}
+
+/// Sign a transaction of one instruction, paid for by the wallet, and send it.
+pub fn send_instruction(
+    config: &Config,
+    client: &RpcClient,
+    instr: Instruction,
+) -> Result<TransactionReport> {
+    let blockhash = client.get_latest_blockhash()?;
+
+    let tx = Transaction::new_signed_with_payer(
+        &[instr],
+        Some(&config.keypair().pubkey()),
+        &[config.keypair()],
+        blockhash,
+    );
+
+    send_and_confirm(config, client, &tx)
+}
+
+/// Simulate a transaction, reporting its logs, errors,
+/// and how it would change the accounts it references.
+///
+/// Nothing is charged, but the fee payer must still exist,
+/// with enough lamports to pay the fee,
+/// even when signatures aren't verified.
+///
+/// Demos also use this for transactions that are expected to fail,
+/// since a failed simulation still returns its logs.
+pub fn simulate_transaction(
+    client: &RpcClient,
+    tx: &Transaction,
+    simulate: SimulateConfig,
+) -> Result<TransactionReport> {
+    let account_keys = &tx.message.account_keys;
+    let before = client.get_multiple_accounts(account_keys)?;
+
+    let sim_config = RpcSimulateTransactionConfig {
+        sig_verify: simulate.sig_verify,
+        replace_recent_blockhash: simulate.replace_blockhash,
+        accounts: Some(RpcSimulateTransactionAccountsConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            addresses: account_keys.iter().map(Pubkey::to_string).collect(),
+        }),
+        ..RpcSimulateTransactionConfig::default()
+    };
+    let result = client
+        .simulate_transaction_with_config(tx, sim_config)?
+        .value;
+
+    // The fee payer is checked before anything runs, so there are no logs to report.
+    if matches!(
+        result.err,
+        Some(TransactionError::AccountNotFound | TransactionError::InsufficientFundsForFee)
+    ) {
+        bail!(
+            "simulation needs the fee payer {} to exist with enough lamports for the fee",
+            account_keys[0]
+        );
+    }
+
+    let logs = result.logs.unwrap_or_default();
+    let mut invocations = logs::parse_logs(&logs);
+    logs::assign_instruction_indexes(&mut invocations, account_keys, &tx.message.instructions);
+
+    let after = result.accounts.unwrap_or_default();
+    let account_changes = account_keys
+        .iter()
+        .zip(before)
+        .zip(after)
+        .filter_map(|((pubkey, before), after)| {
+            let after = after.and_then(|account| account.decode::<Account>());
+            AccountChange::new(*pubkey, before.as_ref(), after.as_ref())
+        })
+        .collect();
+
+    let mut report = TransactionReport::new(tx.signatures[0], logs, invocations);
+    if let Some(units_consumed) = result.units_consumed {
+        report.units_consumed = units_consumed;
+    }
+    report.simulation = Some(SimulationReport {
+        err: result.err.map(|e| e.to_string()),
+        account_changes,
+    });
+
+    Ok(report)
+}
+
+/// Fetch the log messages of a confirmed transaction.
+///
+/// `getTransaction` doesn't accept `processed` commitment,
+/// and a transaction confirmed at `processed` may not yet be visible at `confirmed`,
+/// so this retries for a little while.
+fn get_transaction_logs(
+    config: &Config,
+    client: &RpcClient,
+    sig: &Signature,
+) -> Result<Vec<String>> {
+    let commitment = if config.commitment.is_at_least_confirmed() {
+        config.commitment
+    } else {
+        CommitmentConfig::confirmed()
+    };
+    let tx_config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Json),
+        commitment: Some(commitment),
+        ..RpcTransactionConfig::default()
+    };
+
+    let mut retries = 30;
+    let tx = loop {
+        match client.get_transaction_with_config(sig, tx_config) {
+            Ok(tx) => break tx,
+            Err(e) if retries > 0 => {
+                info!("waiting for transaction {}: {}", sig, e);
+                retries -= 1;
+                thread::sleep(Duration::from_millis(500));
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("unable to get transaction {}", sig));
+            }
+        }
+    };
+
+    let logs = tx
+        .transaction
+        .meta
+        .and_then(|meta| meta.log_messages)
+        .unwrap_or_default();
+
+    Ok(logs)
+}
+
+/// Get the program id from `--program-id`, or else from the deploy keypair,
+/// and check that the program is deployed.
+pub fn get_program_id(client: &RpcClient, program_id: Option<Pubkey>) -> Result<Pubkey> {
+    let program_id = match program_id {
+        Some(program_id) => program_id,
+        None => get_program_keypair()?.pubkey(),
+    };
 
     info!("program id: {}", program_id);
 
+    check_program_deployed(client, &program_id)?;
+
+    Ok(program_id)
+}
+
+/// Like `get_program_id`, for `caller-program`.
+pub fn get_caller_program_id(client: &RpcClient, program_id: Option<Pubkey>) -> Result<Pubkey> {
+    let program_id = match program_id {
+        Some(program_id) => program_id,
+        None => load_deploy_keypair(CALLER_PROGRAM_KEYPAIR_PATH)?.pubkey(),
+    };
+
+    info!("caller program id: {}", program_id);
+
+    check_program_deployed(client, &program_id)?;
+
+    Ok(program_id)
+}
+
+fn check_program_deployed(client: &RpcClient, program_id: &Pubkey) -> Result<()> {
     let account = client
-        .get_account(&program_id)
+        .get_account(program_id)
         .context("unable to get program account")?;
 
     info!("program account: {:?}", account);
@@ -79,5 +499,23 @@ pub fn get_program_keypair(client: &RpcClient) -> Result<Keypair> {
         bail!("solana account not executable");
     }
 
+    Ok(())
+}
+
+pub fn get_program_keypair() -> Result<Keypair> {
+    load_deploy_keypair(PROGRAM_KEYPAIR_PATH)
+}
+
+fn load_deploy_keypair(keypair_path: &str) -> Result<Keypair> {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let deploy_path = format!("{}/../../{}", manifest_dir, DEPLOY_PATH);
+    let program_keypair_path = format!("{}/{}", deploy_path, keypair_path);
+
+    info!("loading program keypair from {}", program_keypair_path);
+
+    let program_keypair = read_keypair_file(&program_keypair_path)
+        .map_err(|e| anyhow!("{}", e))
+        .context("unable to load program keypair")?;
+
     Ok(program_keypair)
 }
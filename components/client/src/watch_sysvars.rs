@@ -0,0 +1,389 @@
+use crate::output;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    account::Account,
+    sysvar::{
+        self, clock::Clock, epoch_schedule::EpochSchedule, rent::Rent, slot_hashes::SlotHashes,
+        slot_history::SlotHistory, stake_history::StakeHistory,
+    },
+};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(clap::Args)]
+pub struct WatchSysvarsOpts {
+    /// Seconds between polls.
+    #[clap(long, default_value = "1")]
+    pub interval: u64,
+    /// Stop after this many seconds, otherwise run until interrupted.
+    #[clap(long)]
+    pub duration: Option<u64>,
+    /// Append timestamped changes to this file.
+    #[clap(long)]
+    pub log: Option<String>,
+}
+
+/// The decoded sysvars at one point in time.
+struct Snapshot {
+    clock: Clock,
+    epoch_schedule: EpochSchedule,
+    rent: Rent,
+    slot_hashes: SlotHashes,
+    slot_history: SlotHistory,
+    stake_history: StakeHistory,
+}
+
+/// The changes seen by one poll.
+#[derive(Serialize, Debug)]
+pub struct WatchEvent {
+    pub timestamp: String,
+    pub slot: u64,
+    /// The first poll has nothing to compare against.
+    pub initial: bool,
+    pub changes: Vec<SysvarChange>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SysvarChange {
+    pub sysvar: &'static str,
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta: Option<i128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl SysvarChange {
+    fn to_text(&self) -> String {
+        let mut text = format!(
+            "{}.{}: {} -> {}",
+            self.sysvar, self.field, self.old, self.new
+        );
+        if let Some(delta) = self.delta {
+            text.push_str(&format!(" ({:+})", delta));
+        }
+        if let Some(note) = &self.note {
+            text.push_str(&format!(" - {}", note));
+        }
+        text
+    }
+}
+
+impl WatchEvent {
+    fn text_lines(&self) -> Vec<String> {
+        if self.initial {
+            vec![format!(
+                "{} watching sysvars from slot {}",
+                self.timestamp, self.slot
+            )]
+        } else {
+            self.changes
+                .iter()
+                .map(|change| format!("{} {}", self.timestamp, change.to_text()))
+                .collect()
+        }
+    }
+}
+
+/// Poll the sysvar accounts and print field-level changes between polls.
+///
+/// Polling rather than subscribing keeps all the sysvars
+/// in one consistent `get_multiple_accounts` snapshot.
+pub fn watch_sysvars(
+    config: &crate::util::Config,
+    client: &RpcClient,
+    opts: WatchSysvarsOpts,
+) -> Result<()> {
+    let format = config.output;
+    let interval = Duration::from_secs(opts.interval);
+    let duration = opts.duration.map(Duration::from_secs);
+
+    let mut log_file = match &opts.log {
+        Some(path) => Some(OpenOptions::new().create(true).append(true).open(path)?),
+        None => None,
+    };
+
+    let start = Instant::now();
+    let mut previous: Option<Snapshot> = None;
+
+    loop {
+        let snapshot = poll(client)?;
+
+        let event = WatchEvent {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            slot: snapshot.clock.slot,
+            initial: previous.is_none(),
+            changes: match &previous {
+                Some(previous) => diff(previous, &snapshot),
+                None => vec![],
+            },
+        };
+
+        if event.initial || !event.changes.is_empty() {
+            output::print_event(format, "sysvars", &event, |event| {
+                for line in event.text_lines() {
+                    println!("{}", line);
+                }
+            });
+
+            if let Some(log_file) = &mut log_file {
+                write_log(log_file, &event)?;
+            }
+        }
+
+        previous = Some(snapshot);
+
+        if let Some(duration) = duration {
+            if start.elapsed() + interval > duration {
+                break;
+            }
+        }
+
+        thread::sleep(interval);
+    }
+
+    Ok(())
+}
+
+fn write_log(log_file: &mut File, event: &WatchEvent) -> Result<()> {
+    for line in event.text_lines() {
+        writeln!(log_file, "{}", line)?;
+    }
+    log_file.flush()?;
+
+    Ok(())
+}
+
+fn poll(client: &RpcClient) -> Result<Snapshot> {
+    let ids = [
+        sysvar::clock::ID,
+        sysvar::epoch_schedule::ID,
+        sysvar::rent::ID,
+        sysvar::slot_hashes::ID,
+        sysvar::slot_history::ID,
+        sysvar::stake_history::ID,
+    ];
+
+    let accounts = client.get_multiple_accounts(&ids)?;
+    let account = |idx: usize| -> Result<&Account> {
+        accounts[idx]
+            .as_ref()
+            .ok_or_else(|| anyhow!("sysvar {} not found", ids[idx]))
+    };
+
+    Ok(Snapshot {
+        clock: bincode::deserialize(&account(0)?.data)?,
+        epoch_schedule: bincode::deserialize(&account(1)?.data)?,
+        rent: bincode::deserialize(&account(2)?.data)?,
+        slot_hashes: bincode::deserialize(&account(3)?.data)?,
+        slot_history: bincode::deserialize(&account(4)?.data)?,
+        stake_history: bincode::deserialize(&account(5)?.data)?,
+    })
+}
+
+fn diff(old: &Snapshot, new: &Snapshot) -> Vec<SysvarChange> {
+    let mut changes = vec![];
+
+    {
+        let (old, new) = (&old.clock, &new.clock);
+        compare_count(&mut changes, "clock", "slot", old.slot, new.slot);
+        compare_count(
+            &mut changes,
+            "clock",
+            "epoch_start_timestamp",
+            old.epoch_start_timestamp,
+            new.epoch_start_timestamp,
+        );
+        compare_count(&mut changes, "clock", "epoch", old.epoch, new.epoch);
+        compare_count(
+            &mut changes,
+            "clock",
+            "leader_schedule_epoch",
+            old.leader_schedule_epoch,
+            new.leader_schedule_epoch,
+        );
+        compare_count(
+            &mut changes,
+            "clock",
+            "unix_timestamp",
+            old.unix_timestamp,
+            new.unix_timestamp,
+        );
+    }
+
+    {
+        let (old_schedule, new_schedule) = (&old.epoch_schedule, &new.epoch_schedule);
+        compare(
+            &mut changes,
+            "epoch_schedule",
+            "slots_per_epoch",
+            &old_schedule.slots_per_epoch,
+            &new_schedule.slots_per_epoch,
+        );
+        compare(
+            &mut changes,
+            "epoch_schedule",
+            "leader_schedule_slot_offset",
+            &old_schedule.leader_schedule_slot_offset,
+            &new_schedule.leader_schedule_slot_offset,
+        );
+        compare(
+            &mut changes,
+            "epoch_schedule",
+            "warmup",
+            &old_schedule.warmup,
+            &new_schedule.warmup,
+        );
+        compare(
+            &mut changes,
+            "epoch_schedule",
+            "first_normal_epoch",
+            &old_schedule.first_normal_epoch,
+            &new_schedule.first_normal_epoch,
+        );
+        compare(
+            &mut changes,
+            "epoch_schedule",
+            "first_normal_slot",
+            &old_schedule.first_normal_slot,
+            &new_schedule.first_normal_slot,
+        );
+
+        // The epoch the clock is in, according to the schedule.
+        let (old_epoch, _) = old_schedule.get_epoch_and_slot_index(old.clock.slot);
+        let (new_epoch, new_slot_index) = new_schedule.get_epoch_and_slot_index(new.clock.slot);
+        if old_epoch != new_epoch {
+            changes.push(SysvarChange {
+                sysvar: "epoch_schedule",
+                field: "epoch_boundary".to_string(),
+                old: old_epoch.into(),
+                new: new_epoch.into(),
+                delta: None,
+                note: Some(format!(
+                    "epoch {} began at slot {}, {} slots per epoch, now at slot index {}",
+                    new_epoch,
+                    new_schedule.get_first_slot_in_epoch(new_epoch),
+                    new_schedule.get_slots_in_epoch(new_epoch),
+                    new_slot_index,
+                )),
+            });
+        }
+    }
+
+    {
+        let (old, new) = (&old.rent, &new.rent);
+        compare(
+            &mut changes,
+            "rent",
+            "lamports_per_byte_year",
+            &old.lamports_per_byte_year,
+            &new.lamports_per_byte_year,
+        );
+        compare(
+            &mut changes,
+            "rent",
+            "exemption_threshold",
+            &old.exemption_threshold,
+            &new.exemption_threshold,
+        );
+        compare(
+            &mut changes,
+            "rent",
+            "burn_percent",
+            &old.burn_percent,
+            &new.burn_percent,
+        );
+    }
+
+    {
+        let old_slots: HashSet<_> = old.slot_hashes.iter().map(|(slot, _)| *slot).collect();
+        for (slot, hash) in new.slot_hashes.iter().rev() {
+            if !old_slots.contains(slot) {
+                changes.push(SysvarChange {
+                    sysvar: "slot_hashes",
+                    field: format!("slot {}", slot),
+                    old: Value::Null,
+                    new: hash.to_string().into(),
+                    delta: None,
+                    note: None,
+                });
+            }
+        }
+    }
+
+    compare_count(
+        &mut changes,
+        "slot_history",
+        "next_slot",
+        old.slot_history.next_slot,
+        new.slot_history.next_slot,
+    );
+
+    {
+        let old_epochs: HashSet<_> = old.stake_history.iter().map(|(epoch, _)| *epoch).collect();
+        for (epoch, entry) in new.stake_history.iter().rev() {
+            if !old_epochs.contains(epoch) {
+                changes.push(SysvarChange {
+                    sysvar: "stake_history",
+                    field: format!("epoch {}", epoch),
+                    old: Value::Null,
+                    new: serde_json::json!({
+                        "effective": entry.effective,
+                        "activating": entry.activating,
+                        "deactivating": entry.deactivating,
+                    }),
+                    delta: None,
+                    note: None,
+                });
+            }
+        }
+    }
+
+    changes
+}
+
+fn compare<T: PartialEq + Serialize>(
+    changes: &mut Vec<SysvarChange>,
+    sysvar: &'static str,
+    field: &str,
+    old: &T,
+    new: &T,
+) {
+    if old != new {
+        changes.push(SysvarChange {
+            sysvar,
+            field: field.to_string(),
+            old: serde_json::to_value(old).unwrap_or(Value::Null),
+            new: serde_json::to_value(new).unwrap_or(Value::Null),
+            delta: None,
+            note: None,
+        });
+    }
+}
+
+fn compare_count<T: PartialEq + Serialize + Copy + Into<i128>>(
+    changes: &mut Vec<SysvarChange>,
+    sysvar: &'static str,
+    field: &str,
+    old: T,
+    new: T,
+) {
+    if old != new {
+        changes.push(SysvarChange {
+            sysvar,
+            field: field.to_string(),
+            old: serde_json::to_value(old).unwrap_or(Value::Null),
+            new: serde_json::to_value(new).unwrap_or(Value::Null),
+            delta: Some(new.into() - old.into()),
+            note: None,
+        });
+    }
+}
@@ -1,10 +1,15 @@
 use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(any(feature = "sysvars", feature = "sysprog", feature = "state"))]
+use solana_program::system_program;
 use solana_program::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
-    system_program, sysvar,
+    sysvar,
 };
+use std::io;
 
+/// Every variant is defined whichever demos are enabled,
+/// so that their borsh tags don't change.
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub enum CustomInstruction {
     PrintSysvars(PrintSysvarsInstruction),
@@ -15,6 +20,35 @@ pub enum CustomInstruction {
     DemoInvoke(DemoInvokeInstruction),
     DemoSystemProgramCreateAccount(DemoSystemProgramCreateAccountInstruction),
     DemoSystemProgramTransferAllocAssign(DemoSystemProgramTransferAllocAssignInstruction),
+    ReadSysvars(ReadSysvarsInstruction),
+    IntrospectInstructions(IntrospectInstructionsInstruction),
+    CheckPolicies(CheckPoliciesInstruction),
+    Settle(SettleInstruction),
+    DemoSystemProgramCreateAccountWithSeed(DemoSystemProgramCreateAccountWithSeedInstruction),
+    DemoSystemProgramAllocateWithSeed(DemoSystemProgramAllocateWithSeedInstruction),
+    DemoSystemProgramAssignWithSeed(DemoSystemProgramAssignWithSeedInstruction),
+    DemoSystemProgramTransferWithSeed(DemoSystemProgramTransferWithSeedInstruction),
+    DemoSystemProgramNonce(DemoSystemProgramNonceInstruction),
+    CloseAccounts(CloseAccountsInstruction),
+    InitializeState(InitializeStateInstruction),
+    UpdateState(UpdateStateInstruction),
+    ReadState(ReadStateInstruction),
+    ResizeAccount(ResizeAccountInstruction),
+    CreateStateAccounts(CreateStateAccountsInstruction),
+}
+
+/// A type an instruction returns as its return data.
+///
+/// Return data is borsh-encoded,
+/// and each instruction documents which type it returns.
+pub trait ReturnData: BorshSerialize + BorshDeserialize {
+    fn encode_return_data(&self) -> io::Result<Vec<u8>> {
+        self.try_to_vec()
+    }
+
+    fn decode_return_data(data: &[u8]) -> io::Result<Self> {
+        Self::try_from_slice(data)
+    }
 }
 
 /// # Accounts
@@ -38,8 +72,22 @@ pub enum PrintSysvarsSection {
     One,
     Two,
     Three,
+    /// Print as many sections as fit in `compute_units`,
+    /// the budget the client requested from the compute budget program.
+    ///
+    /// The program can't query its remaining compute units,
+    /// so the client measures what each section costs beforehand,
+    /// from the checkpoints logged when printing the sections one by one.
+    Dynamic {
+        compute_units: u32,
+        /// Units spent before the first section.
+        base_units: u32,
+        /// Units spent by sections `One`, `Two` and `Three`.
+        section_units: [u32; 3],
+    },
 }
 
+#[cfg(feature = "sysvars")]
 impl PrintSysvarsInstruction {
     pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
         let instr = CustomInstruction::PrintSysvars(self);
@@ -64,6 +112,7 @@ impl PrintSysvarsInstruction {
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct DemoSecp256k1VerifyBasicInstruction;
 
+#[cfg(feature = "secp256k1")]
 impl DemoSecp256k1VerifyBasicInstruction {
     pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
         let instr = CustomInstruction::DemoSecp256k1VerifyBasic(self);
@@ -79,6 +128,7 @@ impl DemoSecp256k1VerifyBasicInstruction {
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct DemoSecp256k1CustomManyInstruction;
 
+#[cfg(feature = "secp256k1")]
 impl DemoSecp256k1CustomManyInstruction {
     pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
         let instr = CustomInstruction::DemoSecp256k1CustomMany(self);
@@ -91,6 +141,10 @@ impl DemoSecp256k1CustomManyInstruction {
 /// # Accounts
 ///
 /// None
+///
+/// # Return data
+///
+/// `Secp256k1RecoverReturnData`
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct DemoSecp256k1RecoverInstruction {
     pub message: Vec<u8>,
@@ -98,6 +152,18 @@ pub struct DemoSecp256k1RecoverInstruction {
     pub recovery_id: u8,
 }
 
+/// The return data of `DemoSecp256k1RecoverInstruction`.
+#[cfg(feature = "secp256k1")]
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct Secp256k1RecoverReturnData {
+    /// The recovered 64-byte public key, without the `0x04` prefix.
+    pub pubkey: [u8; 64],
+}
+
+#[cfg(feature = "secp256k1")]
+impl ReturnData for Secp256k1RecoverReturnData {}
+
+#[cfg(feature = "secp256k1")]
 impl DemoSecp256k1RecoverInstruction {
     pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
         let instr = CustomInstruction::DemoSecp256k1Recover(self);
@@ -113,6 +179,7 @@ impl DemoSecp256k1RecoverInstruction {
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct DemoEd25519Instruction;
 
+#[cfg(feature = "ed25519")]
 impl DemoEd25519Instruction {
     pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
         let instr = CustomInstruction::DemoEd25519(self);
@@ -125,6 +192,11 @@ impl DemoEd25519Instruction {
 /// # Accounts
 ///
 /// - 0: this program id - executable
+/// - 1: instructions sysvar
+///
+/// # Return data
+///
+/// Depends on the mode.
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct DemoInvokeInstruction {
     pub mode: DemoInvokeMode,
@@ -133,13 +205,67 @@ pub struct DemoInvokeInstruction {
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub enum DemoInvokeMode {
     Caller,
+    /// Returns `InvokeReturnData`.
     Callee,
+    /// Like `Callee`, but refuses to run via CPI.
+    Guarded,
+    /// Like `Caller`, but invokes `Guarded`, which fails.
+    CallerOfGuarded,
+    /// Invoke itself until `depth` reaches `max_depth`,
+    /// each level appending its depth to a `RecursiveReturnData`.
+    ///
+    /// The top-level instruction has depth 1.
+    /// Returns `RecursiveReturnData`.
+    Recursive {
+        depth: u8,
+        max_depth: u8,
+    },
+    /// Set return data, then invoke `ReturnDataCallee` with each `ReturnDataAction`,
+    /// checking what return data is left after each.
+    ReturnDataCaller,
+    ReturnDataCallee(ReturnDataAction),
+}
+
+/// What `DemoInvokeMode::ReturnDataCallee` does with the return data.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReturnDataAction {
+    /// Overwrite it with an `InvokeReturnData`.
+    Set,
+    /// Set it, then clear it by setting empty return data.
+    Clear,
+    /// Don't touch it.
+    Leave,
+}
+
+/// The return data of `DemoInvokeMode::Callee` and `DemoInvokeMode::ReturnDataCallee`.
+#[cfg(feature = "invoke")]
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct InvokeReturnData {
+    pub message: String,
 }
 
+#[cfg(feature = "invoke")]
+impl ReturnData for InvokeReturnData {}
+
+/// The return data of `DemoInvokeMode::Recursive`.
+#[cfg(feature = "invoke")]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct RecursiveReturnData {
+    /// The depth of each level, deepest first.
+    pub depths: Vec<u8>,
+}
+
+#[cfg(feature = "invoke")]
+impl ReturnData for RecursiveReturnData {}
+
+#[cfg(feature = "invoke")]
 impl DemoInvokeInstruction {
     pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
         let instr = CustomInstruction::DemoInvoke(self);
-        let accounts = vec![AccountMeta::new_readonly(program_id.clone(), false)];
+        let accounts = vec![
+            AccountMeta::new_readonly(program_id.clone(), false),
+            AccountMeta::new_readonly(sysvar::instructions::ID, false),
+        ];
 
         Instruction::new_with_borsh(*program_id, &instr, accounts)
     }
@@ -166,6 +292,7 @@ pub struct DemoSystemProgramCreateAccountInstruction {
     pub new_account_bump_seed: u8,
 }
 
+#[cfg(feature = "sysprog")]
 impl DemoSystemProgramCreateAccountInstruction {
     pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
         let accounts = vec![
@@ -200,6 +327,7 @@ pub struct DemoSystemProgramTransferAllocAssignInstruction {
     pub new_account_bump_seed: u8,
 }
 
+#[cfg(feature = "sysprog")]
 impl DemoSystemProgramTransferAllocAssignInstruction {
     pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
         let accounts = vec![
@@ -212,3 +340,640 @@ impl DemoSystemProgramTransferAllocAssignInstruction {
         Instruction::new_with_borsh(*program_id, &instr, accounts)
     }
 }
+
+/// An address derived with `Pubkey::create_with_seed`,
+/// from a base that is a PDA of this program.
+///
+/// The program signs for the base with `invoke_signed`,
+/// which is what the `*_with_seed` system instructions need.
+/// The address itself never signs.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct SeedAddress {
+    // This is serialized redundantly with the account meta,
+    // just to avoid adding another param to build_instruction.
+    pub payer: Pubkey,
+    // This is serialized redundantly with the account meta,
+    // just to avoid adding another param to build_instruction.
+    pub base_pda: Pubkey,
+    // This is serialized redundantly with the account meta,
+    // just to avoid adding another param to build_instruction.
+    pub address: Pubkey,
+    /// Random numbers.
+    ///
+    /// The base is derived from the payer and this seed.
+    pub base_seed: [u8; 16],
+    /// Bump seed for the base.
+    pub base_bump_seed: u8,
+    /// The seed passed to `Pubkey::create_with_seed`, at most 32 bytes.
+    pub seed: String,
+}
+
+#[cfg(feature = "sysprog")]
+impl SeedAddress {
+    fn account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(self.payer, true),
+            AccountMeta::new_readonly(self.base_pda, false),
+            AccountMeta::new(self.address, false),
+        ]
+    }
+}
+
+/// # Accounts
+///
+/// - 0: system program - executable
+/// - 1: payer - signer, writable
+/// - 2: base - PDA of this program
+/// - 3: new account - writable, derived with this program as owner
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct DemoSystemProgramCreateAccountWithSeedInstruction {
+    pub seed_address: SeedAddress,
+}
+
+#[cfg(feature = "sysprog")]
+impl DemoSystemProgramCreateAccountWithSeedInstruction {
+    pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
+        let accounts = self.seed_address.account_metas();
+        let instr = CustomInstruction::DemoSystemProgramCreateAccountWithSeed(self);
+
+        Instruction::new_with_borsh(*program_id, &instr, accounts)
+    }
+}
+
+/// # Accounts
+///
+/// - 0: system program - executable
+/// - 1: payer - signer, writable
+/// - 2: base - PDA of this program
+/// - 3: new account - writable, derived with this program as owner
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct DemoSystemProgramAllocateWithSeedInstruction {
+    pub seed_address: SeedAddress,
+}
+
+#[cfg(feature = "sysprog")]
+impl DemoSystemProgramAllocateWithSeedInstruction {
+    pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
+        let accounts = self.seed_address.account_metas();
+        let instr = CustomInstruction::DemoSystemProgramAllocateWithSeed(self);
+
+        Instruction::new_with_borsh(*program_id, &instr, accounts)
+    }
+}
+
+/// # Accounts
+///
+/// - 0: system program - executable
+/// - 1: payer - signer, writable
+/// - 2: base - PDA of this program
+/// - 3: new account - writable, derived with this program as owner
+///
+/// The account has no data, so the program closes it again
+/// before the instruction ends, refunding the payer.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct DemoSystemProgramAssignWithSeedInstruction {
+    pub seed_address: SeedAddress,
+}
+
+#[cfg(feature = "sysprog")]
+impl DemoSystemProgramAssignWithSeedInstruction {
+    pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
+        let accounts = self.seed_address.account_metas();
+        let instr = CustomInstruction::DemoSystemProgramAssignWithSeed(self);
+
+        Instruction::new_with_borsh(*program_id, &instr, accounts)
+    }
+}
+
+/// # Accounts
+///
+/// - 0: system program - executable
+/// - 1: payer - signer, writable
+/// - 2: base - PDA of this program
+/// - 3: seed account - writable, derived with the system program as owner
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct DemoSystemProgramTransferWithSeedInstruction {
+    pub seed_address: SeedAddress,
+}
+
+#[cfg(feature = "sysprog")]
+impl DemoSystemProgramTransferWithSeedInstruction {
+    pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
+        let accounts = self.seed_address.account_metas();
+        let instr = CustomInstruction::DemoSystemProgramTransferWithSeed(self);
+
+        Instruction::new_with_borsh(*program_id, &instr, accounts)
+    }
+}
+
+/// # Accounts
+///
+/// - 0: system program - executable
+/// - 1: payer - signer, writable
+/// - 2: nonce account - writable, PDA of this program
+/// - 3: nonce authority - PDA of this program
+/// - 4: recent blockhashes sysvar
+/// - 5: rent sysvar
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct DemoSystemProgramNonceInstruction {
+    // This is serialized redundantly with the account meta,
+    // just to avoid adding another param to build_instruction.
+    pub payer: Pubkey,
+    /// Derived from the payer and `nonce_account_seed`.
+    pub nonce_account_pda: Pubkey,
+    /// Derived from the nonce account.
+    pub nonce_authority_pda: Pubkey,
+    /// Random numbers.
+    pub nonce_account_seed: [u8; 16],
+    pub nonce_account_bump_seed: u8,
+    pub nonce_authority_bump_seed: u8,
+    pub action: NonceAction,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Copy, Clone)]
+pub enum NonceAction {
+    /// Create and initialize the nonce account, with the authority PDA as its authority.
+    Create { lamports: u64 },
+    /// Fails unless the blockhash has changed since the nonce was last set.
+    Advance,
+    /// Withdraw to the payer.
+    Withdraw { lamports: u64 },
+    /// After this the program can no longer use the nonce account.
+    Authorize { new_authority: Pubkey },
+}
+
+#[cfg(feature = "sysprog")]
+impl DemoSystemProgramNonceInstruction {
+    pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
+        #[allow(deprecated)]
+        let accounts = vec![
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(self.payer, true),
+            AccountMeta::new(self.nonce_account_pda, false),
+            AccountMeta::new_readonly(self.nonce_authority_pda, false),
+            AccountMeta::new_readonly(sysvar::recent_blockhashes::ID, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+        ];
+        let instr = CustomInstruction::DemoSystemProgramNonce(self);
+
+        Instruction::new_with_borsh(*program_id, &instr, accounts)
+    }
+}
+
+/// The data of the accounts this program creates.
+///
+/// Accounts start with `DISCRIMINATOR`, so they can't be mistaken
+/// for some other account of the same size, then a version,
+/// then the creator, so clients can find their accounts
+/// with a `memcmp` filter at `CREATOR_OFFSET`.
+///
+/// A layout change must bump `VERSION` and keep `LEN` or grow it;
+/// `decode` rejects versions it doesn't know.
+/// Accounts may be resized to more than `LEN`,
+/// but not less, with `ResizeAccountInstruction`.
+///
+/// # Return data
+///
+/// `ReadStateInstruction` returns this.
+#[cfg(feature = "state")]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AccountState {
+    pub discriminator: [u8; 8],
+    pub version: u8,
+    pub creator: Pubkey,
+    /// Set by the creator with `UpdateStateInstruction`.
+    pub value: u64,
+    /// How many times the value has been updated.
+    pub updates: u64,
+}
+
+#[cfg(feature = "state")]
+impl ReturnData for AccountState {}
+
+#[cfg(feature = "state")]
+impl AccountState {
+    pub const DISCRIMINATOR: [u8; 8] = *b"DEMOACCT";
+    pub const VERSION: u8 = 1;
+    pub const LEN: usize = 8 + 1 + 32 + 8 + 8;
+    pub const DISCRIMINATOR_OFFSET: usize = 0;
+    pub const CREATOR_OFFSET: usize = 9;
+
+    pub fn new(creator: Pubkey, value: u64) -> AccountState {
+        AccountState {
+            discriminator: AccountState::DISCRIMINATOR,
+            version: AccountState::VERSION,
+            creator,
+            value,
+            updates: 0,
+        }
+    }
+
+    /// Decode an account's data, checking its discriminator and version.
+    pub fn decode(data: &[u8]) -> Result<AccountState, AccountStateError> {
+        if data.len() < AccountState::LEN {
+            return Err(AccountStateError::WrongSize(data.len()));
+        }
+        // Resized accounts have space after the state.
+        let data = &data[..AccountState::LEN];
+        if data.iter().all(|byte| *byte == 0) {
+            return Err(AccountStateError::Uninitialized);
+        }
+        if data[..8] != AccountState::DISCRIMINATOR {
+            return Err(AccountStateError::WrongDiscriminator);
+        }
+        if data[8] != AccountState::VERSION {
+            return Err(AccountStateError::UnsupportedVersion(data[8]));
+        }
+
+        AccountState::try_from_slice(data).map_err(|_| AccountStateError::InvalidData)
+    }
+}
+
+#[cfg(feature = "state")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AccountStateError {
+    WrongSize(usize),
+    Uninitialized,
+    WrongDiscriminator,
+    UnsupportedVersion(u8),
+    InvalidData,
+}
+
+#[cfg(feature = "state")]
+impl std::fmt::Display for AccountStateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AccountStateError::WrongSize(len) => {
+                write!(
+                    f,
+                    "data is {} bytes, expected at least {}",
+                    len,
+                    AccountState::LEN
+                )
+            }
+            AccountStateError::Uninitialized => write!(f, "account is not initialized"),
+            AccountStateError::WrongDiscriminator => write!(f, "not an account state"),
+            AccountStateError::UnsupportedVersion(version) => {
+                write!(f, "unsupported version {}", version)
+            }
+            AccountStateError::InvalidData => write!(f, "invalid account state"),
+        }
+    }
+}
+
+#[cfg(feature = "state")]
+impl std::error::Error for AccountStateError {}
+
+/// Create a state account and initialize it.
+///
+/// Tolerates the address having been sent lamports already.
+///
+/// # Accounts
+///
+/// - 0: system program - executable
+/// - 1: payer - signer, writable, becomes the creator
+/// - 2: state account - writable, PDA of this program
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct InitializeStateInstruction {
+    // This is serialized redundantly with the account meta,
+    // just to avoid adding another param to build_instruction.
+    pub payer: Pubkey,
+    pub state_pda: Pubkey,
+    /// Random numbers.
+    pub state_seed: [u8; 16],
+    pub state_bump_seed: u8,
+    pub value: u64,
+}
+
+#[cfg(feature = "state")]
+impl InitializeStateInstruction {
+    pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
+        let accounts = vec![
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(self.payer, true),
+            AccountMeta::new(self.state_pda, false),
+        ];
+        let instr = CustomInstruction::InitializeState(self);
+
+        Instruction::new_with_borsh(*program_id, &instr, accounts)
+    }
+}
+
+/// Create and initialize many state accounts in one instruction.
+///
+/// Like `InitializeStateInstruction`, with a value of 0,
+/// but the addresses are derived from the seeds instead of being serialized,
+/// to fit as many as possible in a transaction.
+///
+/// # Accounts
+///
+/// - 0: system program - executable
+/// - 1: payer - signer, writable, becomes the creator
+/// - 2..: state accounts - writable, PDAs of this program, one per seed
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct CreateStateAccountsInstruction {
+    // This is serialized redundantly with the account meta,
+    // just to avoid adding another param to build_instruction.
+    pub payer: Pubkey,
+    pub seeds: Vec<StateSeed>,
+}
+
+/// Derives a state PDA from the payer, `seed` and `bump_seed`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Copy, Clone)]
+pub struct StateSeed {
+    /// Random numbers.
+    pub seed: [u8; 16],
+    pub bump_seed: u8,
+}
+
+#[cfg(feature = "state")]
+impl StateSeed {
+    pub fn state_pda(&self, payer: &Pubkey, program_id: &Pubkey) -> Pubkey {
+        Pubkey::create_program_address(&[payer.as_ref(), &self.seed, &[self.bump_seed]], program_id)
+            .expect("bump seed does not derive a PDA")
+    }
+}
+
+#[cfg(feature = "state")]
+impl CreateStateAccountsInstruction {
+    pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(self.payer, true),
+        ];
+        accounts.extend(
+            self.seeds
+                .iter()
+                .map(|seed| AccountMeta::new(seed.state_pda(&self.payer, program_id), false)),
+        );
+        let instr = CustomInstruction::CreateStateAccounts(self);
+
+        Instruction::new_with_borsh(*program_id, &instr, accounts)
+    }
+}
+
+/// # Accounts
+///
+/// - 0: creator - signer
+/// - 1: state account - writable, owned by this program
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct UpdateStateInstruction {
+    // This is serialized redundantly with the account meta,
+    // just to avoid adding another param to build_instruction.
+    pub creator: Pubkey,
+    pub state_account: Pubkey,
+    pub value: u64,
+}
+
+#[cfg(feature = "state")]
+impl UpdateStateInstruction {
+    pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
+        let accounts = vec![
+            AccountMeta::new_readonly(self.creator, true),
+            AccountMeta::new(self.state_account, false),
+        ];
+        let instr = CustomInstruction::UpdateState(self);
+
+        Instruction::new_with_borsh(*program_id, &instr, accounts)
+    }
+}
+
+/// # Accounts
+///
+/// - 0: state account - owned by this program
+///
+/// # Return data
+///
+/// `AccountState`
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct ReadStateInstruction {
+    pub state_account: Pubkey,
+}
+
+#[cfg(feature = "state")]
+impl ReadStateInstruction {
+    pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
+        let accounts = vec![AccountMeta::new_readonly(self.state_account, false)];
+        let instr = CustomInstruction::ReadState(self);
+
+        Instruction::new_with_borsh(*program_id, &instr, accounts)
+    }
+}
+
+/// Grow or shrink a state account, keeping it rent-exempt.
+///
+/// Growing takes the extra rent from the payer,
+/// and shrinking refunds what is no longer needed to the payer.
+///
+/// # Accounts
+///
+/// - 0: system program - executable
+/// - 1: payer - signer, writable, the creator of the account
+/// - 2: state account - writable, owned by this program
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct ResizeAccountInstruction {
+    // This is serialized redundantly with the account meta,
+    // just to avoid adding another param to build_instruction.
+    pub payer: Pubkey,
+    pub state_account: Pubkey,
+    /// At least `AccountState::LEN`.
+    pub new_len: u64,
+}
+
+#[cfg(feature = "state")]
+impl ResizeAccountInstruction {
+    pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
+        let accounts = vec![
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(self.payer, true),
+            AccountMeta::new(self.state_account, false),
+        ];
+        let instr = CustomInstruction::ResizeAccount(self);
+
+        Instruction::new_with_borsh(*program_id, &instr, accounts)
+    }
+}
+
+/// Close state accounts, returning their lamports to their creator.
+///
+/// # Accounts
+///
+/// - 0: payer - signer, writable, the creator of every account
+/// - 1..: accounts to close - writable, owned by this program
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct CloseAccountsInstruction {
+    // This is serialized redundantly with the account meta,
+    // just to avoid adding another param to build_instruction.
+    pub payer: Pubkey,
+    pub accounts: Vec<Pubkey>,
+}
+
+#[cfg(feature = "state")]
+impl CloseAccountsInstruction {
+    pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
+        let mut accounts = vec![AccountMeta::new(self.payer, true)];
+        accounts.extend(
+            self.accounts
+                .iter()
+                .map(|pubkey| AccountMeta::new(*pubkey, false)),
+        );
+        let instr = CustomInstruction::CloseAccounts(self);
+
+        Instruction::new_with_borsh(*program_id, &instr, accounts)
+    }
+}
+
+/// # Accounts
+///
+/// With `SysvarAccess::Accounts`:
+///
+/// - 0: clock
+/// - 1: epoch_schedule
+/// - 2: instructions
+/// - 3: rent
+/// - 4: slot_hashes
+/// - 5: slot_history
+/// - 6: stake_history
+///
+/// With `SysvarAccess::Syscalls`: none
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct ReadSysvarsInstruction {
+    pub access: SysvarAccess,
+}
+
+/// How `ReadSysvarsInstruction` reads the sysvars.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SysvarAccess {
+    /// From the sysvar accounts passed to the instruction.
+    Accounts,
+    /// With `Sysvar::get`, where there is a syscall for it.
+    Syscalls,
+}
+
+impl ReadSysvarsInstruction {
+    pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
+        let accounts = match self.access {
+            SysvarAccess::Accounts => vec![
+                AccountMeta::new_readonly(sysvar::clock::ID, false),
+                AccountMeta::new_readonly(sysvar::epoch_schedule::ID, false),
+                AccountMeta::new_readonly(sysvar::instructions::ID, false),
+                AccountMeta::new_readonly(sysvar::rent::ID, false),
+                AccountMeta::new_readonly(sysvar::slot_hashes::ID, false),
+                AccountMeta::new_readonly(sysvar::slot_history::ID, false),
+                AccountMeta::new_readonly(sysvar::stake_history::ID, false),
+            ],
+            SysvarAccess::Syscalls => vec![],
+        };
+        let instr = CustomInstruction::ReadSysvars(self);
+
+        Instruction::new_with_borsh(*program_id, &instr, accounts)
+    }
+}
+
+/// # Accounts
+///
+/// - 0: instructions sysvar
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct IntrospectInstructionsInstruction;
+
+#[cfg(feature = "introspect")]
+impl IntrospectInstructionsInstruction {
+    pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
+        let instr = CustomInstruction::IntrospectInstructions(self);
+        let accounts = vec![AccountMeta::new_readonly(sysvar::instructions::ID, false)];
+
+        Instruction::new_with_borsh(*program_id, &instr, accounts)
+    }
+}
+
+/// # Accounts
+///
+/// - 0: instructions sysvar
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct CheckPoliciesInstruction {
+    pub policies: Vec<Policy>,
+}
+
+/// A rule about the transaction a `CheckPoliciesInstruction` is part of.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub enum Policy {
+    /// This must be the last instruction in the transaction.
+    MustBeLast,
+    /// No other instruction in the transaction may invoke this program.
+    NoOtherInstructionFor(Pubkey),
+    /// A `SettleInstruction` with this id must follow later in the transaction.
+    SettleFollows { id: u64 },
+}
+
+impl CheckPoliciesInstruction {
+    pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
+        let instr = CustomInstruction::CheckPolicies(self);
+        let accounts = vec![AccountMeta::new_readonly(sysvar::instructions::ID, false)];
+
+        Instruction::new_with_borsh(*program_id, &instr, accounts)
+    }
+}
+
+/// Completes a `Policy::SettleFollows`.
+///
+/// # Accounts
+///
+/// None
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct SettleInstruction {
+    pub id: u64,
+}
+
+impl SettleInstruction {
+    pub fn build_instruction(self, program_id: &Pubkey) -> Instruction {
+        let instr = CustomInstruction::Settle(self);
+        let accounts = vec![];
+
+        Instruction::new_with_borsh(*program_id, &instr, accounts)
+    }
+}
+
+/// Instructions for `caller-program`,
+/// each of which invokes one of this program's instructions via CPI.
+///
+/// # Accounts
+///
+/// - 0: this program - executable
+/// - 1..: the accounts of the invoked instruction
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub enum CallerInstruction {
+    /// Only sections two and three can be printed via CPI:
+    /// section one checks its instruction data against the instructions sysvar,
+    /// which only has the top-level instruction.
+    PrintSysvars(PrintSysvarsInstruction),
+    /// Returns the callee's `Secp256k1RecoverReturnData`.
+    Secp256k1Recover(DemoSecp256k1RecoverInstruction),
+    CreateAccount(DemoSystemProgramCreateAccountInstruction),
+}
+
+#[cfg(all(feature = "sysvars", feature = "secp256k1", feature = "sysprog"))]
+impl CallerInstruction {
+    pub fn build_instruction(self, caller_program_id: &Pubkey, program_id: &Pubkey) -> Instruction {
+        let data = self.try_to_vec().expect("serialize caller instruction");
+        let invoked_instruction = self.invoked_instruction(program_id);
+
+        let mut accounts = vec![AccountMeta::new_readonly(*program_id, false)];
+        accounts.extend(invoked_instruction.accounts);
+
+        Instruction {
+            program_id: *caller_program_id,
+            accounts,
+            data,
+        }
+    }
+
+    /// The instruction `caller-program` invokes.
+    pub fn invoked_instruction(self, program_id: &Pubkey) -> Instruction {
+        match self {
+            CallerInstruction::PrintSysvars(instr) => instr.build_instruction(program_id),
+            CallerInstruction::Secp256k1Recover(instr) => instr.build_instruction(program_id),
+            CallerInstruction::CreateAccount(instr) => instr.build_instruction(program_id),
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! Labelled compute unit checkpoints.
+//!
+//! The client parses these from the transaction logs
+//! and attributes the units spent since the previous checkpoint
+//! to each label.
+
+use solana_program::{log::sol_log_compute_units, msg};
+
+/// Log `checkpoint: <label>` followed by the remaining compute units.
+pub fn checkpoint(label: &str) {
+    msg!("checkpoint: {}", label);
+    sol_log_compute_units();
+}
+
+/// Log the first checkpoint and measure the cost of a checkpoint itself,
+/// which the client subtracts from the later ones.
+pub fn start() {
+    checkpoint("start");
+    checkpoint("overhead");
+}
@@ -0,0 +1,61 @@
+use crate::state;
+use common::CloseAccountsInstruction;
+use solana_program::{
+    account_info::next_account_info, account_info::AccountInfo, entrypoint::ProgramResult, msg,
+    program_error::ProgramError, pubkey::Pubkey, system_program,
+};
+
+/// Close state accounts, such as those the system program demos create,
+/// moving their lamports to the payer who created them.
+///
+/// An account with no lamports is only deleted at the end of the transaction,
+/// and until then a later instruction could fund it again,
+/// reviving it with whatever data and owner it still has.
+/// So each account is also zeroed, shrunk to nothing,
+/// and given back to the system program:
+/// if it is revived it is a plain system account,
+/// not one this program would recognize.
+pub fn close_accounts(
+    program_id: &Pubkey,
+    instruction: CloseAccountsInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    msg!("close accounts");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let payer = next_account_info(account_info_iter)?;
+    assert!(payer.is_signer);
+    assert!(payer.is_writable);
+    assert_eq!(*payer.key, instruction.payer);
+
+    for expected_key in &instruction.accounts {
+        let account = next_account_info(account_info_iter)?;
+        assert_eq!(account.key, expected_key);
+        assert!(account.is_writable);
+
+        // This also rejects an account passed twice,
+        // since the first close hands it to the system program.
+        let state = state::load(program_id, account)?;
+        if state.creator != *payer.key {
+            msg!("{} was created by {}", account.key, state.creator);
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let lamports = account.lamports();
+        **payer.lamports.borrow_mut() = payer
+            .lamports()
+            .checked_add(lamports)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        **account.lamports.borrow_mut() = 0;
+
+        // The runtime only lets an owner reassign an account whose data is zeroed.
+        account.data.borrow_mut().fill(0);
+        account.realloc(0, false)?;
+        account.assign(&system_program::ID);
+
+        msg!("closed {}, reclaimed {} lamports", account.key, lamports);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use crate::policy;
+use borsh::de::BorshDeserialize;
+use common::{CheckPoliciesInstruction, CustomInstruction, Policy, SettleInstruction};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    msg,
+    pubkey::Pubkey,
+};
+
+/// Fail unless the transaction satisfies every policy.
+pub fn check_policies(
+    program_id: &Pubkey,
+    instruction: CheckPoliciesInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    msg!("check policies");
+
+    let account_info_iter = &mut accounts.iter();
+    let instructions_sysvar_account = next_account_info(account_info_iter)?;
+
+    let (current_index, instructions) = policy::load_instructions(instructions_sysvar_account)?;
+
+    for p in instruction.policies {
+        msg!("checking {:?}", p);
+
+        match p {
+            Policy::MustBeLast => {
+                policy::log_violation(policy::must_be_last(current_index, &instructions))?;
+            }
+            Policy::NoOtherInstructionFor(other_program_id) => {
+                policy::log_violation(policy::no_other_instruction_for(
+                    &other_program_id,
+                    current_index,
+                    &instructions,
+                ))?;
+            }
+            Policy::SettleFollows { id } => {
+                let settle_index = policy::log_violation(policy::settle_follows(
+                    current_index,
+                    &instructions,
+                    |instr| is_settle(program_id, instr, id),
+                ))?;
+                msg!("settled by instruction {}", settle_index);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn settle(instruction: SettleInstruction) -> ProgramResult {
+    msg!("settle {}", instruction.id);
+
+    Ok(())
+}
+
+fn is_settle(program_id: &Pubkey, instr: &Instruction, id: u64) -> bool {
+    if instr.program_id != *program_id {
+        return false;
+    }
+
+    let mut data = &instr.data[..];
+    matches!(
+        CustomInstruction::deserialize(&mut data),
+        Ok(CustomInstruction::Settle(SettleInstruction { id: settle_id })) if settle_id == id
+    )
+}
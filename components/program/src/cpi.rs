@@ -0,0 +1,526 @@
+//! Wrappers for calling this program's instructions via CPI.
+//!
+//! Each takes the `AccountInfo`s its instruction needs, in a struct,
+//! and `signers_seeds` for any PDA that signs; pass `&[]` when none do.
+//! `program` is this program's executable account.
+//!
+//! Enabled by the `cpi` feature, which also disables the entrypoint
+//! so that other programs can depend on this crate.
+//! Only the wrappers for the demos the crate is built with are defined.
+
+#[cfg(feature = "ed25519")]
+use common::DemoEd25519Instruction;
+#[cfg(feature = "invoke")]
+use common::DemoInvokeInstruction;
+#[cfg(feature = "introspect")]
+use common::IntrospectInstructionsInstruction;
+#[cfg(feature = "sysvars")]
+use common::PrintSysvarsInstruction;
+use common::{CheckPoliciesInstruction, ReadSysvarsInstruction, SettleInstruction, SysvarAccess};
+#[cfg(feature = "state")]
+use common::{
+    CloseAccountsInstruction, CreateStateAccountsInstruction, InitializeStateInstruction,
+    ReadStateInstruction, ResizeAccountInstruction, UpdateStateInstruction,
+};
+#[cfg(feature = "secp256k1")]
+use common::{
+    DemoSecp256k1CustomManyInstruction, DemoSecp256k1RecoverInstruction,
+    DemoSecp256k1VerifyBasicInstruction,
+};
+#[cfg(feature = "sysprog")]
+use common::{
+    DemoSystemProgramAllocateWithSeedInstruction, DemoSystemProgramAssignWithSeedInstruction,
+    DemoSystemProgramCreateAccountInstruction, DemoSystemProgramCreateAccountWithSeedInstruction,
+    DemoSystemProgramNonceInstruction, DemoSystemProgramTransferAllocAssignInstruction,
+    DemoSystemProgramTransferWithSeedInstruction,
+};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program::invoke_signed,
+    program_error::ProgramError,
+};
+
+pub struct PrintSysvarsAccounts<'a> {
+    pub system_program: AccountInfo<'a>,
+    pub clock: AccountInfo<'a>,
+    pub epoch_schedule: AccountInfo<'a>,
+    pub instructions: AccountInfo<'a>,
+    pub rent: AccountInfo<'a>,
+    pub slot_hashes: AccountInfo<'a>,
+    pub slot_history: AccountInfo<'a>,
+    pub stake_history: AccountInfo<'a>,
+}
+
+/// Accounts for the instructions whose only account is the instructions sysvar.
+pub struct InstructionsSysvarAccounts<'a> {
+    pub instructions: AccountInfo<'a>,
+}
+
+pub struct SystemProgramAccounts<'a> {
+    pub system_program: AccountInfo<'a>,
+    pub payer: AccountInfo<'a>,
+    pub new_account: AccountInfo<'a>,
+}
+
+/// Accounts for the `*_with_seed` instructions.
+pub struct SeedAddressAccounts<'a> {
+    pub system_program: AccountInfo<'a>,
+    pub payer: AccountInfo<'a>,
+    pub base: AccountInfo<'a>,
+    pub address: AccountInfo<'a>,
+}
+
+pub struct UpdateStateAccounts<'a> {
+    pub creator: AccountInfo<'a>,
+    pub state_account: AccountInfo<'a>,
+}
+
+pub struct NonceAccounts<'a> {
+    pub system_program: AccountInfo<'a>,
+    pub payer: AccountInfo<'a>,
+    pub nonce_account: AccountInfo<'a>,
+    pub nonce_authority: AccountInfo<'a>,
+    pub recent_blockhashes: AccountInfo<'a>,
+    pub rent: AccountInfo<'a>,
+}
+
+/// Only needed with `SysvarAccess::Accounts`.
+pub struct ReadSysvarsAccounts<'a> {
+    pub clock: AccountInfo<'a>,
+    pub epoch_schedule: AccountInfo<'a>,
+    pub instructions: AccountInfo<'a>,
+    pub rent: AccountInfo<'a>,
+    pub slot_hashes: AccountInfo<'a>,
+    pub slot_history: AccountInfo<'a>,
+    pub stake_history: AccountInfo<'a>,
+}
+
+/// Only sections two and three can be printed via CPI:
+/// section one checks its instruction data against the instructions sysvar,
+/// which only has the top-level instruction.
+#[cfg(feature = "sysvars")]
+pub fn print_sysvars<'a>(
+    program: &AccountInfo<'a>,
+    instruction: PrintSysvarsInstruction,
+    accounts: PrintSysvarsAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &[
+            program.clone(),
+            accounts.system_program,
+            accounts.clock,
+            accounts.epoch_schedule,
+            accounts.instructions,
+            accounts.rent,
+            accounts.slot_hashes,
+            accounts.slot_history,
+            accounts.stake_history,
+        ],
+        signers_seeds,
+    )
+}
+
+/// The secp256k1 instruction must be in the top-level transaction.
+#[cfg(feature = "secp256k1")]
+pub fn demo_secp256k1_verify_basic<'a>(
+    program: &AccountInfo<'a>,
+    instruction: DemoSecp256k1VerifyBasicInstruction,
+    accounts: InstructionsSysvarAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &[program.clone(), accounts.instructions],
+        signers_seeds,
+    )
+}
+
+/// The secp256k1 instruction must be in the top-level transaction.
+#[cfg(feature = "secp256k1")]
+pub fn demo_secp256k1_custom_many<'a>(
+    program: &AccountInfo<'a>,
+    instruction: DemoSecp256k1CustomManyInstruction,
+    accounts: InstructionsSysvarAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &[program.clone(), accounts.instructions],
+        signers_seeds,
+    )
+}
+
+/// Sets a `Secp256k1RecoverReturnData` as return data.
+#[cfg(feature = "secp256k1")]
+pub fn demo_secp256k1_recover<'a>(
+    program: &AccountInfo<'a>,
+    instruction: DemoSecp256k1RecoverInstruction,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(&instr, &[program.clone()], signers_seeds)
+}
+
+/// The ed25519 instruction must be in the top-level transaction.
+#[cfg(feature = "ed25519")]
+pub fn demo_ed25519<'a>(
+    program: &AccountInfo<'a>,
+    instruction: DemoEd25519Instruction,
+    accounts: InstructionsSysvarAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &[program.clone(), accounts.instructions],
+        signers_seeds,
+    )
+}
+
+#[cfg(feature = "invoke")]
+pub fn demo_invoke<'a>(
+    program: &AccountInfo<'a>,
+    instruction: DemoInvokeInstruction,
+    accounts: InstructionsSysvarAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &[program.clone(), accounts.instructions],
+        signers_seeds,
+    )
+}
+
+#[cfg(feature = "sysprog")]
+pub fn demo_system_program_create_account<'a>(
+    program: &AccountInfo<'a>,
+    instruction: DemoSystemProgramCreateAccountInstruction,
+    accounts: SystemProgramAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &[
+            program.clone(),
+            accounts.system_program,
+            accounts.payer,
+            accounts.new_account,
+        ],
+        signers_seeds,
+    )
+}
+
+#[cfg(feature = "sysprog")]
+pub fn demo_system_program_transfer_alloc_assign<'a>(
+    program: &AccountInfo<'a>,
+    instruction: DemoSystemProgramTransferAllocAssignInstruction,
+    accounts: SystemProgramAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &[
+            program.clone(),
+            accounts.system_program,
+            accounts.payer,
+            accounts.new_account,
+        ],
+        signers_seeds,
+    )
+}
+
+#[cfg(feature = "sysprog")]
+pub fn demo_system_program_create_account_with_seed<'a>(
+    program: &AccountInfo<'a>,
+    instruction: DemoSystemProgramCreateAccountWithSeedInstruction,
+    accounts: SeedAddressAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &seed_address_account_infos(program, accounts),
+        signers_seeds,
+    )
+}
+
+#[cfg(feature = "sysprog")]
+pub fn demo_system_program_allocate_with_seed<'a>(
+    program: &AccountInfo<'a>,
+    instruction: DemoSystemProgramAllocateWithSeedInstruction,
+    accounts: SeedAddressAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &seed_address_account_infos(program, accounts),
+        signers_seeds,
+    )
+}
+
+#[cfg(feature = "sysprog")]
+pub fn demo_system_program_assign_with_seed<'a>(
+    program: &AccountInfo<'a>,
+    instruction: DemoSystemProgramAssignWithSeedInstruction,
+    accounts: SeedAddressAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &seed_address_account_infos(program, accounts),
+        signers_seeds,
+    )
+}
+
+#[cfg(feature = "sysprog")]
+pub fn demo_system_program_transfer_with_seed<'a>(
+    program: &AccountInfo<'a>,
+    instruction: DemoSystemProgramTransferWithSeedInstruction,
+    accounts: SeedAddressAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &seed_address_account_infos(program, accounts),
+        signers_seeds,
+    )
+}
+
+fn seed_address_account_infos<'a>(
+    program: &AccountInfo<'a>,
+    accounts: SeedAddressAccounts<'a>,
+) -> [AccountInfo<'a>; 5] {
+    [
+        program.clone(),
+        accounts.system_program,
+        accounts.payer,
+        accounts.base,
+        accounts.address,
+    ]
+}
+
+#[cfg(feature = "sysprog")]
+pub fn demo_system_program_nonce<'a>(
+    program: &AccountInfo<'a>,
+    instruction: DemoSystemProgramNonceInstruction,
+    accounts: NonceAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &[
+            program.clone(),
+            accounts.system_program,
+            accounts.payer,
+            accounts.nonce_account,
+            accounts.nonce_authority,
+            accounts.recent_blockhashes,
+            accounts.rent,
+        ],
+        signers_seeds,
+    )
+}
+
+/// `accounts` are the accounts to close, in the order of `instruction.accounts`.
+#[cfg(feature = "state")]
+pub fn close_accounts<'a>(
+    program: &AccountInfo<'a>,
+    instruction: CloseAccountsInstruction,
+    payer: AccountInfo<'a>,
+    accounts: Vec<AccountInfo<'a>>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    let mut account_infos = vec![program.clone(), payer];
+    account_infos.extend(accounts);
+
+    invoke_signed(&instr, &account_infos, signers_seeds)
+}
+
+/// `accounts.new_account` is the state PDA.
+#[cfg(feature = "state")]
+pub fn initialize_state<'a>(
+    program: &AccountInfo<'a>,
+    instruction: InitializeStateInstruction,
+    accounts: SystemProgramAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &[
+            program.clone(),
+            accounts.system_program,
+            accounts.payer,
+            accounts.new_account,
+        ],
+        signers_seeds,
+    )
+}
+
+/// `state_accounts` are in the order of `instruction.seeds`.
+#[cfg(feature = "state")]
+pub fn create_state_accounts<'a>(
+    program: &AccountInfo<'a>,
+    instruction: CreateStateAccountsInstruction,
+    system_program: AccountInfo<'a>,
+    payer: AccountInfo<'a>,
+    state_accounts: Vec<AccountInfo<'a>>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    let mut account_infos = vec![program.clone(), system_program, payer];
+    account_infos.extend(state_accounts);
+
+    invoke_signed(&instr, &account_infos, signers_seeds)
+}
+
+#[cfg(feature = "state")]
+pub fn update_state<'a>(
+    program: &AccountInfo<'a>,
+    instruction: UpdateStateInstruction,
+    accounts: UpdateStateAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &[program.clone(), accounts.creator, accounts.state_account],
+        signers_seeds,
+    )
+}
+
+/// The state is left as return data for the caller to decode.
+#[cfg(feature = "state")]
+pub fn read_state<'a>(
+    program: &AccountInfo<'a>,
+    instruction: ReadStateInstruction,
+    state_account: AccountInfo<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(&instr, &[program.clone(), state_account], signers_seeds)
+}
+
+/// `accounts.new_account` is the state account.
+#[cfg(feature = "state")]
+pub fn resize_account<'a>(
+    program: &AccountInfo<'a>,
+    instruction: ResizeAccountInstruction,
+    accounts: SystemProgramAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &[
+            program.clone(),
+            accounts.system_program,
+            accounts.payer,
+            accounts.new_account,
+        ],
+        signers_seeds,
+    )
+}
+
+/// `accounts` must be `Some` with `SysvarAccess::Accounts`.
+pub fn read_sysvars<'a>(
+    program: &AccountInfo<'a>,
+    instruction: ReadSysvarsInstruction,
+    accounts: Option<ReadSysvarsAccounts<'a>>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let account_infos = match (instruction.access, accounts) {
+        (SysvarAccess::Accounts, Some(accounts)) => vec![
+            program.clone(),
+            accounts.clock,
+            accounts.epoch_schedule,
+            accounts.instructions,
+            accounts.rent,
+            accounts.slot_hashes,
+            accounts.slot_history,
+            accounts.stake_history,
+        ],
+        (SysvarAccess::Accounts, None) => return Err(ProgramError::NotEnoughAccountKeys),
+        (SysvarAccess::Syscalls, _) => vec![program.clone()],
+    };
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(&instr, &account_infos, signers_seeds)
+}
+
+/// Logs the top-level instructions, not the CPI.
+#[cfg(feature = "introspect")]
+pub fn introspect_instructions<'a>(
+    program: &AccountInfo<'a>,
+    instruction: IntrospectInstructionsInstruction,
+    accounts: InstructionsSysvarAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &[program.clone(), accounts.instructions],
+        signers_seeds,
+    )
+}
+
+/// The policies apply to the top-level instruction being executed,
+/// not the CPI.
+pub fn check_policies<'a>(
+    program: &AccountInfo<'a>,
+    instruction: CheckPoliciesInstruction,
+    accounts: InstructionsSysvarAccounts<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(
+        &instr,
+        &[program.clone(), accounts.instructions],
+        signers_seeds,
+    )
+}
+
+/// A settle made via CPI doesn't satisfy `Policy::SettleFollows`,
+/// which only sees top-level instructions.
+pub fn settle<'a>(
+    program: &AccountInfo<'a>,
+    instruction: SettleInstruction,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instr = instruction.build_instruction(program.key);
+
+    invoke_signed(&instr, &[program.clone()], signers_seeds)
+}
@@ -0,0 +1,53 @@
+//! Detecting whether an instruction is running via CPI.
+//!
+//! The instructions sysvar only contains the transaction's top-level instructions,
+//! so during a CPI the "current" instruction it reports is the caller's.
+//! If that isn't this program's own instruction with this instruction's data,
+//! then this program was invoked by something else.
+//!
+//! A program that invokes itself with identical instruction data
+//! looks the same as a top-level call and can't be detected this way.
+
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+    pubkey::Pubkey, sysvar,
+};
+
+/// Fail if the current instruction was not called directly by the transaction.
+pub fn reject_cpi(
+    program_id: &Pubkey,
+    instructions_sysvar_account: &AccountInfo,
+    instruction_data: &[u8],
+) -> ProgramResult {
+    assert!(sysvar::instructions::check_id(
+        instructions_sysvar_account.key
+    ));
+
+    let current_index =
+        sysvar::instructions::load_current_index_checked(instructions_sysvar_account)?;
+    let current_instr = sysvar::instructions::load_instruction_at_checked(
+        current_index.into(),
+        instructions_sysvar_account,
+    )?;
+
+    if current_instr.program_id != *program_id {
+        msg!(
+            "rejecting CPI: top-level instruction {} is for program {}",
+            current_index,
+            current_instr.program_id
+        );
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    if current_instr.data != instruction_data {
+        msg!(
+            "rejecting CPI: top-level instruction {} is this program's, with other data",
+            current_index
+        );
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    msg!("called top-level as instruction {}", current_index);
+
+    Ok(())
+}
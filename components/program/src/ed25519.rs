@@ -1,3 +1,4 @@
+use crate::ed25519_defs;
 use common::DemoEd25519Instruction;
 use ed25519_dalek::PUBLIC_KEY_LENGTH;
 use solana_program::{
@@ -9,52 +10,6 @@ use solana_program::{
     sysvar,
 };
 
-mod ed25519_defs {
-    use solana_program::program_error::ProgramError;
-
-    pub const PUBKEY_SERIALIZED_SIZE: usize = 32;
-    //pub const SIGNATURE_SERIALIZED_SIZE: usize = 64;
-    //pub const SIGNATURE_OFFSETS_SERIALIZED_SIZE: usize = 14;
-    // bytemuck requires structures to be aligned
-    //pub const SIGNATURE_OFFSETS_START: usize = 2;
-    //pub const DATA_START: usize = SIGNATURE_OFFSETS_SERIALIZED_SIZE + SIGNATURE_OFFSETS_START;
-
-    #[derive(Default, Debug, Copy, Clone)]
-    pub struct Ed25519SignatureOffsets {
-        pub signature_offset: u16, // offset to ed25519 signature of 64 bytes
-        pub signature_instruction_index: u16, // instruction index to find signature
-        pub public_key_offset: u16, // offset to public key of 32 bytes
-        pub public_key_instruction_index: u16, // instruction index to find public key
-        pub message_data_offset: u16, // offset to start of message data
-        pub message_data_size: u16, // size of message data
-        pub message_instruction_index: u16, // index of instruction data to get message data
-    }
-
-    pub fn load_signature_offsets(
-        ed25519_instr_data: &[u8],
-    ) -> Result<Ed25519SignatureOffsets, ProgramError> {
-        let instr_data_slice = ed25519_instr_data
-            .get(2..)
-            .ok_or(ProgramError::InvalidArgument)?;
-
-        fn decode_u16(chunk: &[u8], index: usize) -> u16 {
-            u16::from_le_bytes(<[u8; 2]>::try_from(&chunk[index..index + 2]).unwrap())
-        }
-
-        let offsets = Ed25519SignatureOffsets {
-            signature_offset: decode_u16(instr_data_slice, 0),
-            signature_instruction_index: decode_u16(instr_data_slice, 2),
-            public_key_offset: decode_u16(instr_data_slice, 4),
-            public_key_instruction_index: decode_u16(instr_data_slice, 6),
-            message_data_offset: decode_u16(instr_data_slice, 8),
-            message_data_size: decode_u16(instr_data_slice, 10),
-            message_instruction_index: decode_u16(instr_data_slice, 12),
-        };
-
-        Ok(offsets)
-    }
-}
-
 const AUTHORIZED_ED25519_PUBKEY: [u8; PUBLIC_KEY_LENGTH] = [
     211, 210, 72, 176, 173, 140, 129, 224, 36, 99, 29, 4, 141, 117, 74, 94, 173, 213, 199, 210, 26,
     108, 206, 227, 55, 76, 126, 162, 14, 112, 100, 112,
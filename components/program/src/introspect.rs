@@ -0,0 +1,163 @@
+use crate::ed25519_defs;
+use crate::secp256k1_defs;
+use borsh::de::BorshDeserialize;
+use common::{CustomInstruction, IntrospectInstructionsInstruction};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    ed25519_program,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    secp256k1_program, system_program, sysvar,
+};
+
+/// `SystemInstruction` variant names, by their bincode tag.
+const SYSTEM_INSTRUCTIONS: [&str; 12] = [
+    "CreateAccount",
+    "Assign",
+    "Transfer",
+    "CreateAccountWithSeed",
+    "AdvanceNonceAccount",
+    "WithdrawNonceAccount",
+    "InitializeNonceAccount",
+    "AuthorizeNonceAccount",
+    "Allocate",
+    "AllocateWithSeed",
+    "AssignWithSeed",
+    "TransferWithSeed",
+];
+
+/// Log every instruction in the transaction,
+/// decoding the ones from programs we know.
+pub fn introspect_instructions(
+    program_id: &Pubkey,
+    _instruction: IntrospectInstructionsInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    msg!("introspect instructions");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let instructions_sysvar_account = next_account_info(account_info_iter)?;
+    assert!(sysvar::instructions::check_id(
+        instructions_sysvar_account.key
+    ));
+
+    let num_instructions = load_num_instructions(instructions_sysvar_account)?;
+    let current_index =
+        sysvar::instructions::load_current_index_checked(instructions_sysvar_account)?;
+
+    msg!(
+        "{} instructions, current index {}",
+        num_instructions,
+        current_index
+    );
+
+    for index in 0..num_instructions {
+        let instr = sysvar::instructions::load_instruction_at_checked(
+            index as usize,
+            instructions_sysvar_account,
+        )?;
+
+        let current = if index == current_index {
+            " (current)"
+        } else {
+            ""
+        };
+        msg!(
+            "instruction {}{}: program {}, {} accounts, {} bytes of data",
+            index,
+            current,
+            instr.program_id,
+            instr.accounts.len(),
+            instr.data.len()
+        );
+
+        for (account_index, meta) in instr.accounts.iter().enumerate() {
+            msg!(
+                "  account {}: {} signer: {} writable: {}",
+                account_index,
+                meta.pubkey,
+                meta.is_signer,
+                meta.is_writable
+            );
+        }
+
+        log_decoded_instruction(program_id, &instr)?;
+    }
+
+    Ok(())
+}
+
+/// The instructions sysvar data begins with the number of instructions as a `u16`.
+///
+/// There is no function for this in `sysvar::instructions`.
+fn load_num_instructions(instructions_sysvar_account: &AccountInfo) -> Result<u16, ProgramError> {
+    let data = instructions_sysvar_account.try_borrow_data()?;
+    let bytes = data.get(0..2).ok_or(ProgramError::InvalidAccountData)?;
+
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn log_decoded_instruction(program_id: &Pubkey, instr: &Instruction) -> ProgramResult {
+    if instr.program_id == *program_id {
+        let mut data = &instr.data[..];
+        match CustomInstruction::deserialize(&mut data) {
+            Ok(decoded) => msg!("  this program: {:?}", decoded),
+            Err(e) => msg!("  this program: undecodable: {}", e),
+        }
+    } else if system_program::check_id(&instr.program_id) {
+        log_system_instruction(&instr.data);
+    } else if secp256k1_program::check_id(&instr.program_id) {
+        for (idx, offsets) in secp256k1_defs::iter_signature_offsets(&instr.data)?.enumerate() {
+            msg!(
+                "  secp256k1 signature {}: signature in instruction {}, eth address in instruction {}, {} byte message in instruction {}",
+                idx,
+                offsets.signature_instruction_index,
+                offsets.eth_address_instruction_index,
+                offsets.message_data_size,
+                offsets.message_instruction_index
+            );
+        }
+    } else if ed25519_program::check_id(&instr.program_id) {
+        let num_signatures = *instr.data.get(0).ok_or(ProgramError::InvalidArgument)?;
+        let offsets = ed25519_defs::load_signature_offsets(&instr.data)?;
+        msg!(
+            "  ed25519: {} signatures, first: {:?}",
+            num_signatures,
+            offsets
+        );
+    } else {
+        msg!("  unknown program");
+    }
+
+    Ok(())
+}
+
+/// Decode the fields we care about from a bincode-serialized `SystemInstruction`.
+fn log_system_instruction(data: &[u8]) {
+    let read_u64 = |offset: usize| {
+        data.get(offset..offset + 8)
+            .map(|bytes| u64::from_le_bytes(<[u8; 8]>::try_from(bytes).unwrap()))
+    };
+
+    let tag = data
+        .get(0..4)
+        .map(|bytes| u32::from_le_bytes(<[u8; 4]>::try_from(bytes).unwrap()));
+    let name = tag.and_then(|tag| SYSTEM_INSTRUCTIONS.get(tag as usize));
+
+    match (name, tag) {
+        (Some(name), Some(0)) => msg!(
+            "  system {}: lamports: {:?}, space: {:?}",
+            name,
+            read_u64(4),
+            read_u64(12)
+        ),
+        (Some(name), Some(2)) => msg!("  system {}: lamports: {:?}", name, read_u64(4)),
+        (Some(name), Some(8)) => msg!("  system {}: space: {:?}", name, read_u64(4)),
+        (Some(name), _) => msg!("  system {}", name),
+        (None, _) => msg!("  system: unknown instruction {:?}", tag),
+    }
+}
@@ -1,45 +1,68 @@
-use common::{DemoInvokeInstruction, DemoInvokeMode};
+use crate::cpi_guard;
+use common::{
+    DemoInvokeInstruction, DemoInvokeMode, InvokeReturnData, RecursiveReturnData, ReturnData,
+    ReturnDataAction,
+};
 use solana_program::{
-    account_info::AccountInfo, entrypoint::ProgramResult, msg, program, pubkey::Pubkey,
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg, program,
+    pubkey::Pubkey,
 };
 
 pub fn demo_invoke(
     program_id: &Pubkey,
     instruction: DemoInvokeInstruction,
-    _accounts: &[AccountInfo],
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
 ) -> ProgramResult {
     match instruction.mode {
         DemoInvokeMode::Caller => {
-            do_caller_mode(program_id)?;
+            do_caller_mode(program_id, accounts, DemoInvokeMode::Callee)?;
         }
         DemoInvokeMode::Callee => {
             do_callee_mode()?;
         }
+        DemoInvokeMode::Guarded => {
+            do_guarded_mode(program_id, accounts, instruction_data)?;
+        }
+        DemoInvokeMode::CallerOfGuarded => {
+            do_caller_mode(program_id, accounts, DemoInvokeMode::Guarded)?;
+        }
+        DemoInvokeMode::Recursive { depth, max_depth } => {
+            do_recursive_mode(program_id, accounts, depth, max_depth)?;
+        }
+        DemoInvokeMode::ReturnDataCaller => {
+            do_return_data_caller_mode(program_id, accounts)?;
+        }
+        DemoInvokeMode::ReturnDataCallee(action) => {
+            do_return_data_callee_mode(action)?;
+        }
     }
 
     Ok(())
 }
 
-fn do_caller_mode(program_id: &Pubkey) -> ProgramResult {
+fn do_caller_mode(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    callee_mode: DemoInvokeMode,
+) -> ProgramResult {
     msg!("invoke caller");
 
-    let instr = DemoInvokeInstruction {
-        mode: DemoInvokeMode::Callee,
-    }
-    .build_instruction(program_id);
+    let instr = DemoInvokeInstruction { mode: callee_mode }.build_instruction(program_id);
 
-    program::invoke(&instr, &[])?;
+    // The guarded callee needs the instructions sysvar.
+    // If it rejects the CPI, the whole transaction fails here.
+    program::invoke(&instr, accounts)?;
 
     let return_data = program::get_return_data();
 
     if let Some((return_data_pubkey, return_data)) = return_data {
-        msg!(
-            "return data: ({}, {:?})",
-            return_data_pubkey,
-            std::str::from_utf8(&return_data)
-        );
+        let return_data = InvokeReturnData::decode_return_data(&return_data)?;
+        msg!("return data: ({}, {:?})", return_data_pubkey, return_data);
         assert_eq!(&return_data_pubkey, program_id);
-        assert_eq!(return_data, b"hello world");
+        assert_eq!(return_data.message, "hello world");
     } else {
         panic!("expected return data");
     }
@@ -50,7 +73,132 @@ fn do_caller_mode(program_id: &Pubkey) -> ProgramResult {
 fn do_callee_mode() -> ProgramResult {
     msg!("invoke callee");
 
-    program::set_return_data(b"hello world");
+    let return_data = InvokeReturnData {
+        message: "hello world".to_string(),
+    };
+    program::set_return_data(&return_data.encode_return_data()?);
+
+    Ok(())
+}
+
+fn do_guarded_mode(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    msg!("invoke guarded");
+
+    let account_info_iter = &mut accounts.iter();
+    let _program_account = next_account_info(account_info_iter)?;
+    let instructions_sysvar_account = next_account_info(account_info_iter)?;
+
+    cpi_guard::reject_cpi(program_id, instructions_sysvar_account, instruction_data)?;
+
+    do_callee_mode()
+}
+
+fn do_recursive_mode(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    depth: u8,
+    max_depth: u8,
+) -> ProgramResult {
+    msg!("invoke depth {} of {}", depth, max_depth);
+
+    let mut return_data = RecursiveReturnData::default();
+
+    if depth < max_depth {
+        let instr = DemoInvokeInstruction {
+            mode: DemoInvokeMode::Recursive {
+                depth: depth + 1,
+                max_depth,
+            },
+        }
+        .build_instruction(program_id);
+
+        // Past the runtime's max invoke depth this fails,
+        // and the whole transaction fails with it.
+        program::invoke(&instr, accounts)?;
+
+        let (return_data_pubkey, callee_return_data) =
+            program::get_return_data().expect("expected return data");
+        assert_eq!(&return_data_pubkey, program_id);
+
+        return_data = RecursiveReturnData::decode_return_data(&callee_return_data)?;
+    }
+
+    return_data.depths.push(depth);
+    msg!("depth {} return data: {:?}", depth, return_data);
+
+    program::set_return_data(&return_data.encode_return_data()?);
+
+    Ok(())
+}
+
+fn do_return_data_caller_mode(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    msg!("return data caller");
+
+    let caller_return_data = InvokeReturnData {
+        message: "caller".to_string(),
+    };
+    program::set_return_data(&caller_return_data.encode_return_data()?);
+    msg!("caller set: {:?}", program::get_return_data());
+
+    for action in [
+        ReturnDataAction::Set,
+        ReturnDataAction::Clear,
+        ReturnDataAction::Leave,
+    ] {
+        let instr = DemoInvokeInstruction {
+            mode: DemoInvokeMode::ReturnDataCallee(action),
+        }
+        .build_instruction(program_id);
+
+        program::invoke(&instr, accounts)?;
+
+        let return_data = program::get_return_data();
+        msg!("after callee {:?}: {:?}", action, return_data);
+
+        match action {
+            ReturnDataAction::Set => {
+                let (return_data_pubkey, return_data) = return_data.expect("expected return data");
+                assert_eq!(&return_data_pubkey, program_id);
+                let return_data = InvokeReturnData::decode_return_data(&return_data)?;
+                assert_eq!(return_data.message, "callee");
+            }
+            ReturnDataAction::Clear => {
+                assert_eq!(return_data, None);
+            }
+            // The runtime clears the return data before every CPI,
+            // so the caller's own return data doesn't survive either.
+            ReturnDataAction::Leave => {
+                assert_eq!(return_data, None);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn do_return_data_callee_mode(action: ReturnDataAction) -> ProgramResult {
+    msg!("return data callee: {:?}", action);
+    msg!("callee sees: {:?}", program::get_return_data());
+
+    let return_data = InvokeReturnData {
+        message: "callee".to_string(),
+    }
+    .encode_return_data()?;
+
+    match action {
+        ReturnDataAction::Set => {
+            program::set_return_data(&return_data);
+        }
+        ReturnDataAction::Clear => {
+            program::set_return_data(&return_data);
+            program::set_return_data(&[]);
+        }
+        ReturnDataAction::Leave => {}
+    }
 
     Ok(())
 }
@@ -1,18 +1,47 @@
 use borsh::de::BorshDeserialize;
 use common::CustomInstruction;
 use solana_program::{
-    account_info::AccountInfo, entrypoint, entrypoint::ProgramResult, pubkey::Pubkey,
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+    pubkey::Pubkey,
 };
 
+mod checkpoint;
+#[cfg(feature = "state")]
+mod close;
+mod composition;
+#[cfg(feature = "cpi")]
+pub mod cpi;
+#[cfg(feature = "invoke")]
+mod cpi_guard;
+#[cfg(feature = "ed25519")]
 mod ed25519;
+#[cfg(any(feature = "ed25519", feature = "introspect"))]
+mod ed25519_defs;
+#[cfg(feature = "introspect")]
+mod introspect;
+#[cfg(feature = "invoke")]
 mod invoke;
+#[cfg(feature = "state")]
+mod pda;
+mod policy;
+#[cfg(feature = "state")]
+mod resize;
+#[cfg(feature = "secp256k1")]
 mod secp256k1;
+#[cfg(any(feature = "secp256k1", feature = "introspect"))]
+mod secp256k1_defs;
+#[cfg(feature = "state")]
+mod state;
+#[cfg(feature = "sysprog")]
 mod sysprog;
+mod sysvar_access;
+#[cfg(feature = "sysvars")]
 mod sysvars;
 
-entrypoint!(process_instruction);
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
 
-fn process_instruction(
+pub fn process_instruction(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     instruction_data: &[u8],
@@ -23,30 +52,101 @@ fn process_instruction(
     };
 
     match instruction {
+        #[cfg(feature = "sysvars")]
         CustomInstruction::PrintSysvars(instr) => {
             sysvars::print_sysvars(instr, accounts, instruction_data)?;
         }
+        #[cfg(feature = "secp256k1")]
         CustomInstruction::DemoSecp256k1VerifyBasic(instr) => {
             secp256k1::demo_secp256k1_verify_basic(instr, accounts)?;
         }
+        #[cfg(feature = "secp256k1")]
         CustomInstruction::DemoSecp256k1CustomMany(instr) => {
             secp256k1::demo_secp256k1_custom_many(instr, accounts)?;
         }
+        #[cfg(feature = "secp256k1")]
         CustomInstruction::DemoSecp256k1Recover(instr) => {
             secp256k1::demo_secp256k1_recover(instr, accounts)?;
         }
+        #[cfg(feature = "ed25519")]
         CustomInstruction::DemoEd25519(instr) => {
             ed25519::demo_ed25519(instr, accounts)?;
         }
+        #[cfg(feature = "invoke")]
         CustomInstruction::DemoInvoke(instr) => {
-            invoke::demo_invoke(program_id, instr, accounts)?;
+            invoke::demo_invoke(program_id, instr, accounts, instruction_data)?;
         }
+        #[cfg(feature = "sysprog")]
         CustomInstruction::DemoSystemProgramCreateAccount(instr) => {
             sysprog::demo_system_program_create_account(program_id, instr, accounts)?;
         }
+        #[cfg(feature = "sysprog")]
         CustomInstruction::DemoSystemProgramTransferAllocAssign(instr) => {
             sysprog::demo_system_program_transfer_alloc_assign(program_id, instr, accounts)?;
         }
+        #[cfg(feature = "sysprog")]
+        CustomInstruction::DemoSystemProgramCreateAccountWithSeed(instr) => {
+            sysprog::demo_system_program_create_account_with_seed(program_id, instr, accounts)?;
+        }
+        #[cfg(feature = "sysprog")]
+        CustomInstruction::DemoSystemProgramAllocateWithSeed(instr) => {
+            sysprog::demo_system_program_allocate_with_seed(program_id, instr, accounts)?;
+        }
+        #[cfg(feature = "sysprog")]
+        CustomInstruction::DemoSystemProgramAssignWithSeed(instr) => {
+            sysprog::demo_system_program_assign_with_seed(program_id, instr, accounts)?;
+        }
+        #[cfg(feature = "sysprog")]
+        CustomInstruction::DemoSystemProgramTransferWithSeed(instr) => {
+            sysprog::demo_system_program_transfer_with_seed(program_id, instr, accounts)?;
+        }
+        #[cfg(feature = "sysprog")]
+        CustomInstruction::DemoSystemProgramNonce(instr) => {
+            sysprog::demo_system_program_nonce(program_id, instr, accounts)?;
+        }
+        #[cfg(feature = "state")]
+        CustomInstruction::CloseAccounts(instr) => {
+            close::close_accounts(program_id, instr, accounts)?;
+        }
+        #[cfg(feature = "state")]
+        CustomInstruction::InitializeState(instr) => {
+            state::initialize_state(program_id, instr, accounts)?;
+        }
+        #[cfg(feature = "state")]
+        CustomInstruction::CreateStateAccounts(instr) => {
+            state::create_state_accounts(program_id, instr, accounts)?;
+        }
+        #[cfg(feature = "state")]
+        CustomInstruction::UpdateState(instr) => {
+            state::update_state(program_id, instr, accounts)?;
+        }
+        #[cfg(feature = "state")]
+        CustomInstruction::ReadState(instr) => {
+            state::read_state(program_id, instr, accounts)?;
+        }
+        #[cfg(feature = "state")]
+        CustomInstruction::ResizeAccount(instr) => {
+            resize::resize_account(program_id, instr, accounts)?;
+        }
+        CustomInstruction::ReadSysvars(instr) => {
+            sysvar_access::read_sysvars(instr, accounts)?;
+        }
+        #[cfg(feature = "introspect")]
+        CustomInstruction::IntrospectInstructions(instr) => {
+            introspect::introspect_instructions(program_id, instr, accounts)?;
+        }
+        CustomInstruction::CheckPolicies(instr) => {
+            composition::check_policies(program_id, instr, accounts)?;
+        }
+        CustomInstruction::Settle(instr) => {
+            composition::settle(instr)?;
+        }
+        // Only reachable when a demo's feature is disabled.
+        #[allow(unreachable_patterns)]
+        instruction => {
+            msg!("instruction not compiled in: {:?}", instruction);
+            return Err(ProgramError::InvalidInstructionData);
+        }
     }
 
     Ok(())
@@ -0,0 +1,332 @@
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_instruction, system_program,
+    sysvar::rent::Rent,
+    sysvar::Sysvar,
+};
+
+/// Create a rent-exempt PDA owned by `owner`,
+/// even if someone has already sent lamports to its address.
+///
+/// `create_account` fails if the address has any lamports,
+/// so anyone can block it by sending the address a single lamport.
+/// This uses `create_account` for an empty address,
+/// and otherwise falls back to `top_up_allocate_assign`.
+///
+/// Calling this again for an account it already created
+/// only tops up its lamports, if rent went up.
+pub fn create_or_top_up<'a>(
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    space: usize,
+    owner: &Pubkey,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    if account.lamports() == 0 {
+        let lamports = Rent::get()?.minimum_balance(space);
+        let instr = system_instruction::create_account(
+            payer.key,
+            account.key,
+            lamports,
+            space as u64,
+            owner,
+        );
+
+        invoke_signed(&instr, &[payer.clone(), account.clone()], &[signer_seeds])?;
+
+        msg!("created {} with {} lamports", account.key, lamports);
+
+        return Ok(());
+    }
+
+    top_up_allocate_assign(payer, account, space, owner, signer_seeds)
+}
+
+/// Do whichever of transfer, allocate and assign the account still needs.
+///
+/// Only transfers the shortfall from the rent-exempt minimum,
+/// only allocates a system account with no data,
+/// and only assigns an account not yet owned by `owner`,
+/// so each step can be skipped if it was already done.
+pub fn top_up_allocate_assign<'a>(
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    space: usize,
+    owner: &Pubkey,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let already_owned = account.owner == owner;
+
+    if !already_owned && !system_program::check_id(account.owner) {
+        msg!("{} is owned by {}", account.key, account.owner);
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    if account.data_len() != space && (already_owned || account.data_len() != 0) {
+        msg!(
+            "{} has {} bytes of data, expected {}",
+            account.key,
+            account.data_len(),
+            space
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let required_lamports = Rent::get()?.minimum_balance(space);
+    let shortfall = required_lamports.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        let instr = system_instruction::transfer(payer.key, account.key, shortfall);
+        invoke(&instr, &[payer.clone(), account.clone()])?;
+        msg!("transferred {} lamports to {}", shortfall, account.key);
+    } else {
+        msg!(
+            "{} already has {} lamports",
+            account.key,
+            account.lamports()
+        );
+    }
+
+    if account.data_len() != space {
+        let instr = system_instruction::allocate(account.key, space as u64);
+        invoke_signed(&instr, &[account.clone()], &[signer_seeds])?;
+        msg!("allocated {} bytes", space);
+    }
+
+    if !already_owned {
+        let instr = system_instruction::assign(account.key, owner);
+        invoke_signed(&instr, &[account.clone()], &[signer_seeds])?;
+        msg!("assigned to {}", owner);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::{entrypoint::SUCCESS, instruction::Instruction, program_stubs};
+    use std::cell::RefCell;
+    use std::sync::Once;
+
+    const SPACE: usize = 42;
+
+    thread_local! {
+        static INVOKED: RefCell<Vec<Instruction>> = RefCell::new(vec![]);
+    }
+
+    /// Records the instructions the helpers invoke instead of running them.
+    struct RecordingStubs;
+
+    impl program_stubs::SyscallStubs for RecordingStubs {
+        fn sol_invoke_signed(
+            &self,
+            instruction: &Instruction,
+            _account_infos: &[AccountInfo],
+            _signers_seeds: &[&[&[u8]]],
+        ) -> ProgramResult {
+            INVOKED.with(|invoked| invoked.borrow_mut().push(instruction.clone()));
+            Ok(())
+        }
+
+        fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe { *(var_addr as *mut Rent) = Rent::default() };
+            SUCCESS
+        }
+    }
+
+    struct Fixture {
+        payer: Pubkey,
+        account: Pubkey,
+        program_id: Pubkey,
+    }
+
+    impl Fixture {
+        fn new() -> Fixture {
+            static STUBS: Once = Once::new();
+            STUBS.call_once(|| {
+                program_stubs::set_syscall_stubs(Box::new(RecordingStubs));
+            });
+
+            Fixture {
+                payer: Pubkey::new_unique(),
+                account: Pubkey::new_unique(),
+                program_id: Pubkey::new_unique(),
+            }
+        }
+
+        /// Call `create_or_top_up` on an account in the given state,
+        /// returning the result and the instructions it invoked.
+        fn create_or_top_up(
+            &self,
+            lamports: u64,
+            data_len: usize,
+            owner: &Pubkey,
+        ) -> (ProgramResult, Vec<Instruction>) {
+            let mut payer_lamports = 1_000_000_000;
+            let mut payer_data: [u8; 0] = [];
+            let payer = AccountInfo::new(
+                &self.payer,
+                true,
+                true,
+                &mut payer_lamports,
+                &mut payer_data,
+                &system_program::ID,
+                false,
+                0,
+            );
+
+            let mut account_lamports = lamports;
+            let mut account_data = vec![0; data_len];
+            let account = AccountInfo::new(
+                &self.account,
+                false,
+                true,
+                &mut account_lamports,
+                &mut account_data,
+                owner,
+                false,
+                0,
+            );
+
+            INVOKED.with(|invoked| invoked.borrow_mut().clear());
+            let result = create_or_top_up(&payer, &account, SPACE, &self.program_id, &[b"seed"]);
+            let invoked = INVOKED.with(|invoked| invoked.take());
+
+            (result, invoked)
+        }
+
+        fn transfer(&self, lamports: u64) -> Instruction {
+            system_instruction::transfer(&self.payer, &self.account, lamports)
+        }
+
+        fn allocate(&self) -> Instruction {
+            system_instruction::allocate(&self.account, SPACE as u64)
+        }
+
+        fn assign(&self) -> Instruction {
+            system_instruction::assign(&self.account, &self.program_id)
+        }
+    }
+
+    fn minimum_balance() -> u64 {
+        Rent::default().minimum_balance(SPACE)
+    }
+
+    #[test]
+    fn empty_address_is_created() {
+        let fixture = Fixture::new();
+
+        let (result, invoked) = fixture.create_or_top_up(0, 0, &system_program::ID);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            invoked,
+            vec![system_instruction::create_account(
+                &fixture.payer,
+                &fixture.account,
+                minimum_balance(),
+                SPACE as u64,
+                &fixture.program_id,
+            )]
+        );
+    }
+
+    #[test]
+    fn fully_pre_funded() {
+        let fixture = Fixture::new();
+
+        let (result, invoked) =
+            fixture.create_or_top_up(minimum_balance() + 1, 0, &system_program::ID);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(invoked, vec![fixture.allocate(), fixture.assign()]);
+    }
+
+    #[test]
+    fn partially_funded_below_rent_exempt() {
+        let fixture = Fixture::new();
+
+        let (result, invoked) = fixture.create_or_top_up(1, 0, &system_program::ID);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            invoked,
+            vec![
+                fixture.transfer(minimum_balance() - 1),
+                fixture.allocate(),
+                fixture.assign(),
+            ]
+        );
+    }
+
+    #[test]
+    fn already_allocated() {
+        let fixture = Fixture::new();
+
+        let (result, invoked) = fixture.create_or_top_up(1, SPACE, &system_program::ID);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            invoked,
+            vec![fixture.transfer(minimum_balance() - 1), fixture.assign()]
+        );
+    }
+
+    #[test]
+    fn already_assigned() {
+        let fixture = Fixture::new();
+        let program_id = fixture.program_id;
+
+        let (result, invoked) = fixture.create_or_top_up(minimum_balance(), SPACE, &program_id);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(invoked, vec![]);
+    }
+
+    #[test]
+    fn already_assigned_below_rent_exempt() {
+        let fixture = Fixture::new();
+        let program_id = fixture.program_id;
+
+        let (result, invoked) = fixture.create_or_top_up(1, SPACE, &program_id);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(invoked, vec![fixture.transfer(minimum_balance() - 1)]);
+    }
+
+    #[test]
+    fn owned_by_another_program() {
+        let fixture = Fixture::new();
+
+        let (result, invoked) = fixture.create_or_top_up(1, SPACE, &Pubkey::new_unique());
+
+        assert_eq!(result, Err(ProgramError::IllegalOwner));
+        assert_eq!(invoked, vec![]);
+    }
+
+    #[test]
+    fn system_account_with_wrong_size() {
+        let fixture = Fixture::new();
+
+        let (result, invoked) = fixture.create_or_top_up(1, SPACE + 1, &system_program::ID);
+
+        assert_eq!(result, Err(ProgramError::InvalidAccountData));
+        assert_eq!(invoked, vec![]);
+    }
+
+    #[test]
+    fn assigned_with_wrong_size() {
+        let fixture = Fixture::new();
+        let program_id = fixture.program_id;
+
+        let (result, invoked) = fixture.create_or_top_up(minimum_balance(), 0, &program_id);
+
+        assert_eq!(result, Err(ProgramError::InvalidAccountData));
+        assert_eq!(invoked, vec![]);
+    }
+}
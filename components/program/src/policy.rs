@@ -0,0 +1,294 @@
+//! Transaction composition policies.
+//!
+//! These check the shape of the transaction an instruction is part of,
+//! using the top-level instructions from the instructions sysvar.
+//! The checks are plain functions over a list of instructions,
+//! so they don't depend on the runtime.
+//!
+//! CPIs made by other instructions don't appear in the instructions sysvar,
+//! so these policies can only constrain top-level instructions.
+
+use solana_program::{
+    account_info::AccountInfo, instruction::Instruction, msg, program_error::ProgramError,
+    pubkey::Pubkey, sysvar,
+};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PolicyError {
+    /// The instruction must be last, but is followed by another.
+    NotLast { index: usize, len: usize },
+    /// Another instruction invokes a program that must only be invoked once.
+    OtherInstructionFor { program_id: Pubkey, index: usize },
+    /// No matching settle instruction follows.
+    NoSettle,
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolicyError::NotLast { index, len } => write!(
+                f,
+                "instruction {} must be last, but the transaction has {} instructions",
+                index, len
+            ),
+            PolicyError::OtherInstructionFor { program_id, index } => write!(
+                f,
+                "instruction {} also invokes program {}",
+                index, program_id
+            ),
+            PolicyError::NoSettle => write!(f, "no matching settle instruction follows"),
+        }
+    }
+}
+
+impl From<PolicyError> for ProgramError {
+    fn from(e: PolicyError) -> ProgramError {
+        let code = match e {
+            PolicyError::NotLast { .. } => 0,
+            PolicyError::OtherInstructionFor { .. } => 1,
+            PolicyError::NoSettle => 2,
+        };
+        ProgramError::Custom(code)
+    }
+}
+
+/// The current instruction's index, and every top-level instruction in the transaction.
+pub fn load_instructions(
+    instructions_sysvar_account: &AccountInfo,
+) -> Result<(usize, Vec<Instruction>), ProgramError> {
+    assert!(sysvar::instructions::check_id(
+        instructions_sysvar_account.key
+    ));
+
+    let current_index =
+        sysvar::instructions::load_current_index_checked(instructions_sysvar_account)?;
+
+    // Load instructions until we run off the end.
+    let mut instructions = vec![];
+    while let Ok(instr) = sysvar::instructions::load_instruction_at_checked(
+        instructions.len(),
+        instructions_sysvar_account,
+    ) {
+        instructions.push(instr);
+    }
+
+    Ok((current_index.into(), instructions))
+}
+
+/// The instruction at `current_index` must be the last in the transaction.
+pub fn must_be_last(current_index: usize, instructions: &[Instruction]) -> Result<(), PolicyError> {
+    if current_index + 1 != instructions.len() {
+        return Err(PolicyError::NotLast {
+            index: current_index,
+            len: instructions.len(),
+        });
+    }
+
+    Ok(())
+}
+
+/// No instruction but the one at `current_index` may invoke `program_id`.
+pub fn no_other_instruction_for(
+    program_id: &Pubkey,
+    current_index: usize,
+    instructions: &[Instruction],
+) -> Result<(), PolicyError> {
+    let other = instructions
+        .iter()
+        .enumerate()
+        .find(|(index, instr)| *index != current_index && instr.program_id == *program_id);
+
+    if let Some((index, _)) = other {
+        return Err(PolicyError::OtherInstructionFor {
+            program_id: *program_id,
+            index,
+        });
+    }
+
+    Ok(())
+}
+
+/// An instruction matching `is_settle` must come after the one at `current_index`,
+/// as with a flash loan that must be repaid in the same transaction.
+///
+/// Returns the index of the first matching instruction.
+pub fn settle_follows(
+    current_index: usize,
+    instructions: &[Instruction],
+    is_settle: impl Fn(&Instruction) -> bool,
+) -> Result<usize, PolicyError> {
+    instructions
+        .iter()
+        .enumerate()
+        .skip(current_index + 1)
+        .find(|(_, instr)| is_settle(instr))
+        .map(|(index, _)| index)
+        .ok_or(PolicyError::NoSettle)
+}
+
+/// Log a policy violation before returning it.
+pub fn log_violation<T>(result: Result<T, PolicyError>) -> Result<T, PolicyError> {
+    if let Err(e) = &result {
+        msg!("policy violation: {}", e);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instr(program_id: &Pubkey, data: &[u8]) -> Instruction {
+        Instruction::new_with_bytes(*program_id, data, vec![])
+    }
+
+    const SETTLE: &[u8] = &[1];
+
+    fn is_settle(program_id: Pubkey) -> impl Fn(&Instruction) -> bool {
+        move |instr| instr.program_id == program_id && instr.data == SETTLE
+    }
+
+    #[test]
+    fn must_be_last_when_last() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let instructions = [instr(&a, &[]), instr(&b, &[])];
+
+        assert_eq!(must_be_last(1, &instructions), Ok(()));
+    }
+
+    #[test]
+    fn must_be_last_when_not_last() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let instructions = [instr(&a, &[]), instr(&b, &[])];
+
+        assert_eq!(
+            must_be_last(0, &instructions),
+            Err(PolicyError::NotLast { index: 0, len: 2 })
+        );
+    }
+
+    #[test]
+    fn single_instruction() {
+        let program_id = Pubkey::new_unique();
+        let instructions = [instr(&program_id, &[])];
+
+        assert_eq!(must_be_last(0, &instructions), Ok(()));
+        assert_eq!(
+            no_other_instruction_for(&program_id, 0, &instructions),
+            Ok(())
+        );
+        assert_eq!(
+            settle_follows(0, &instructions, is_settle(program_id)),
+            Err(PolicyError::NoSettle)
+        );
+    }
+
+    #[test]
+    fn other_instruction_for_other_programs() {
+        let (program_id, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let instructions = [
+            instr(&other, &[]),
+            instr(&program_id, &[]),
+            instr(&other, &[]),
+        ];
+
+        assert_eq!(
+            no_other_instruction_for(&program_id, 1, &instructions),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn other_instruction_for_duplicate_program() {
+        let (program_id, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let instructions = [
+            instr(&program_id, &[]),
+            instr(&other, &[]),
+            instr(&program_id, &[]),
+        ];
+
+        assert_eq!(
+            no_other_instruction_for(&program_id, 0, &instructions),
+            Err(PolicyError::OtherInstructionFor {
+                program_id,
+                index: 2
+            })
+        );
+        assert_eq!(
+            no_other_instruction_for(&program_id, 2, &instructions),
+            Err(PolicyError::OtherInstructionFor {
+                program_id,
+                index: 0
+            })
+        );
+    }
+
+    #[test]
+    fn settle_missing() {
+        let (program_id, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let instructions = [
+            instr(&program_id, &[]),
+            instr(&other, SETTLE),
+            instr(&program_id, &[2]),
+        ];
+
+        assert_eq!(
+            settle_follows(0, &instructions, is_settle(program_id)),
+            Err(PolicyError::NoSettle)
+        );
+    }
+
+    #[test]
+    fn settle_before_current() {
+        let program_id = Pubkey::new_unique();
+        let instructions = [instr(&program_id, SETTLE), instr(&program_id, &[])];
+
+        assert_eq!(
+            settle_follows(1, &instructions, is_settle(program_id)),
+            Err(PolicyError::NoSettle)
+        );
+    }
+
+    #[test]
+    fn settle_is_not_current() {
+        let program_id = Pubkey::new_unique();
+        let instructions = [instr(&program_id, SETTLE)];
+
+        assert_eq!(
+            settle_follows(0, &instructions, is_settle(program_id)),
+            Err(PolicyError::NoSettle)
+        );
+    }
+
+    #[test]
+    fn settle_in_last_position() {
+        let (program_id, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let instructions = [
+            instr(&program_id, &[]),
+            instr(&other, &[]),
+            instr(&program_id, SETTLE),
+        ];
+
+        assert_eq!(
+            settle_follows(0, &instructions, is_settle(program_id)),
+            Ok(2)
+        );
+    }
+
+    #[test]
+    fn settle_first_match() {
+        let program_id = Pubkey::new_unique();
+        let instructions = [
+            instr(&program_id, &[]),
+            instr(&program_id, SETTLE),
+            instr(&program_id, SETTLE),
+        ];
+
+        assert_eq!(
+            settle_follows(0, &instructions, is_settle(program_id)),
+            Ok(1)
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use crate::state;
+use common::{AccountState, ResizeAccountInstruction};
+use solana_program::{
+    account_info::next_account_info,
+    account_info::AccountInfo,
+    entrypoint::{ProgramResult, MAX_PERMITTED_DATA_INCREASE},
+    msg,
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_instruction, system_program,
+    sysvar::rent::Rent,
+    sysvar::Sysvar,
+};
+
+/// Resize a state account with `AccountInfo::realloc`.
+///
+/// Growing takes only the shortfall in rent from the payer.
+/// Shrinking refunds everything above the new rent-exempt minimum.
+///
+/// The runtime limits how much an account can grow in one instruction
+/// to `MAX_PERMITTED_DATA_INCREASE`.
+pub fn resize_account(
+    program_id: &Pubkey,
+    instruction: ResizeAccountInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    msg!("resize account");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let system_program_account = next_account_info(account_info_iter)?;
+    assert!(system_program::check_id(system_program_account.key));
+
+    let payer = next_account_info(account_info_iter)?;
+    assert!(payer.is_signer);
+    assert!(payer.is_writable);
+
+    let state_account = next_account_info(account_info_iter)?;
+    assert!(state_account.is_writable);
+
+    let state = state::load(program_id, state_account)?;
+    if state.creator != *payer.key {
+        msg!("{} was created by {}", state_account.key, state.creator);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let old_len = state_account.data_len();
+    let new_len = instruction.new_len as usize;
+    if new_len < AccountState::LEN {
+        msg!("{} bytes can't hold the account state", new_len);
+        return Err(ProgramError::InvalidArgument);
+    }
+    if new_len > old_len + MAX_PERMITTED_DATA_INCREASE {
+        msg!(
+            "can only grow by {} bytes per instruction",
+            MAX_PERMITTED_DATA_INCREASE
+        );
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let rent = Rent::get()?;
+    let old_minimum = rent.minimum_balance(old_len);
+    let new_minimum = rent.minimum_balance(new_len);
+    msg!(
+        "rent: {} lamports per byte-year, exempt after {} years",
+        rent.lamports_per_byte_year,
+        rent.exemption_threshold
+    );
+    msg!(
+        "minimum balance: {} bytes: {} lamports, {} bytes: {} lamports",
+        old_len,
+        old_minimum,
+        new_len,
+        new_minimum
+    );
+
+    let lamports = state_account.lamports();
+    if new_len > old_len {
+        let shortfall = new_minimum.saturating_sub(lamports);
+        if shortfall > 0 {
+            let instr = system_instruction::transfer(payer.key, state_account.key, shortfall);
+            invoke(&instr, &[payer.clone(), state_account.clone()])?;
+        }
+        msg!("took {} lamports from the payer", shortfall);
+
+        // Zero the new bytes, in case the account shrank earlier in this transaction
+        // and the old data is still there.
+        state_account.realloc(new_len, true)?;
+    } else {
+        state_account.realloc(new_len, false)?;
+
+        // The program owns the account, so it can debit it directly.
+        let excess = lamports.saturating_sub(new_minimum);
+        **state_account.lamports.borrow_mut() = lamports - excess;
+        **payer.lamports.borrow_mut() = payer
+            .lamports()
+            .checked_add(excess)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        msg!("refunded {} lamports to the payer", excess);
+    }
+
+    msg!(
+        "resized {} from {} to {} bytes, {} lamports",
+        state_account.key,
+        old_len,
+        new_len,
+        state_account.lamports()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use crate::checkpoint::{self, checkpoint};
+use crate::pda;
+use borsh::BorshSerialize;
+use common::{
+    AccountState, AccountStateError, CreateStateAccountsInstruction, InitializeStateInstruction,
+    ReadStateInstruction, ReturnData, UpdateStateInstruction,
+};
+use solana_program::{
+    account_info::next_account_info, account_info::AccountInfo, entrypoint::ProgramResult, msg,
+    program::set_return_data, program_error::ProgramError, pubkey::Pubkey, system_program,
+};
+
+/// Write a new `AccountState` to an account this program just created.
+///
+/// Fails if the account has already been initialized,
+/// so an existing account can't be reset to a new creator.
+pub fn initialize(
+    program_id: &Pubkey,
+    account: &AccountInfo,
+    creator: &Pubkey,
+    value: u64,
+) -> ProgramResult {
+    assert_eq!(account.owner, program_id);
+
+    match AccountState::decode(&account.data.borrow()) {
+        Err(AccountStateError::Uninitialized) => {}
+        Ok(_) => return Err(ProgramError::AccountAlreadyInitialized),
+        Err(e) => {
+            msg!("{}: {}", account.key, e);
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    store(account, &AccountState::new(*creator, value))
+}
+
+/// Load an account's state, checking its owner, discriminator and version.
+pub fn load(program_id: &Pubkey, account: &AccountInfo) -> Result<AccountState, ProgramError> {
+    if account.owner != program_id {
+        msg!("{} is not owned by this program", account.key);
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    AccountState::decode(&account.data.borrow()).map_err(|e| {
+        msg!("{}: {}", account.key, e);
+        match e {
+            AccountStateError::Uninitialized => ProgramError::UninitializedAccount,
+            _ => ProgramError::InvalidAccountData,
+        }
+    })
+}
+
+fn store(account: &AccountInfo, state: &AccountState) -> ProgramResult {
+    state.serialize(&mut &mut account.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+pub fn initialize_state(
+    program_id: &Pubkey,
+    instruction: InitializeStateInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    msg!("initialize state");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let system_program_account = next_account_info(account_info_iter)?;
+    assert!(system_program::check_id(system_program_account.key));
+
+    let payer = next_account_info(account_info_iter)?;
+    assert!(payer.is_signer);
+    assert!(payer.is_writable);
+
+    let state_pda = next_account_info(account_info_iter)?;
+    assert!(!state_pda.is_signer);
+    assert!(state_pda.is_writable);
+
+    pda::create_or_top_up(
+        payer,
+        state_pda,
+        AccountState::LEN,
+        program_id,
+        &[
+            payer.key.as_ref(),
+            &instruction.state_seed,
+            &[instruction.state_bump_seed],
+        ],
+    )?;
+
+    initialize(program_id, state_pda, payer.key, instruction.value)?;
+
+    msg!("state account: {}", state_pda.key);
+
+    Ok(())
+}
+
+/// Create a state account for each seed, with a checkpoint after each,
+/// so the client can see what one creation costs.
+pub fn create_state_accounts(
+    program_id: &Pubkey,
+    instruction: CreateStateAccountsInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    msg!("create {} state accounts", instruction.seeds.len());
+
+    let account_info_iter = &mut accounts.iter();
+
+    let system_program_account = next_account_info(account_info_iter)?;
+    assert!(system_program::check_id(system_program_account.key));
+
+    let payer = next_account_info(account_info_iter)?;
+    assert!(payer.is_signer);
+    assert!(payer.is_writable);
+
+    checkpoint::start();
+
+    for seed in &instruction.seeds {
+        let state_pda = next_account_info(account_info_iter)?;
+        assert!(!state_pda.is_signer);
+        assert!(state_pda.is_writable);
+
+        // The system program rejects the creation
+        // if the seeds don't derive this address.
+        pda::create_or_top_up(
+            payer,
+            state_pda,
+            AccountState::LEN,
+            program_id,
+            &[payer.key.as_ref(), &seed.seed, &[seed.bump_seed]],
+        )?;
+
+        initialize(program_id, state_pda, payer.key, 0)?;
+
+        checkpoint("create");
+    }
+
+    Ok(())
+}
+
+pub fn update_state(
+    program_id: &Pubkey,
+    instruction: UpdateStateInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    msg!("update state");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let creator = next_account_info(account_info_iter)?;
+    assert!(creator.is_signer);
+
+    let state_account = next_account_info(account_info_iter)?;
+    assert!(state_account.is_writable);
+
+    let mut state = load(program_id, state_account)?;
+    if state.creator != *creator.key {
+        msg!("{} was created by {}", state_account.key, state.creator);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    msg!("value: {} -> {}", state.value, instruction.value);
+    state.value = instruction.value;
+    state.updates = state
+        .updates
+        .checked_add(1)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    store(state_account, &state)
+}
+
+pub fn read_state(
+    program_id: &Pubkey,
+    _instruction: ReadStateInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    msg!("read state");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let state_account = next_account_info(account_info_iter)?;
+
+    let state = load(program_id, state_account)?;
+    msg!("{:?}", state);
+
+    set_return_data(&state.encode_return_data()?);
+
+    Ok(())
+}
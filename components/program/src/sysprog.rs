@@ -1,9 +1,21 @@
+use crate::{pda, state};
 use common::{
-    DemoSystemProgramCreateAccountInstruction, DemoSystemProgramTransferAllocAssignInstruction,
+    AccountState, DemoSystemProgramAllocateWithSeedInstruction,
+    DemoSystemProgramAssignWithSeedInstruction, DemoSystemProgramCreateAccountInstruction,
+    DemoSystemProgramCreateAccountWithSeedInstruction, DemoSystemProgramNonceInstruction,
+    DemoSystemProgramTransferAllocAssignInstruction, DemoSystemProgramTransferWithSeedInstruction,
+    NonceAction, SeedAddress,
 };
 use solana_program::{
-    account_info::next_account_info, account_info::AccountInfo, entrypoint::ProgramResult, msg,
-    program::invoke_signed, pubkey::Pubkey, system_instruction, system_program, sysvar::rent::Rent,
+    account_info::next_account_info,
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg, nonce,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_instruction, system_program, sysvar,
+    sysvar::rent::Rent,
     sysvar::Sysvar,
 };
 
@@ -30,26 +42,24 @@ pub fn demo_system_program_create_account(
     let new_account_seed = &instruction.new_account_seed;
     let new_account_bump_seed = instruction.new_account_bump_seed;
 
-    let space = 1;
-    let rent = Rent::get()?;
-    let lamports = rent.minimum_balance(space);
+    let space = AccountState::LEN;
 
-    let instr = system_instruction::create_account(
-        payer.key,
-        new_account_pda.key,
-        lamports,
-        space as u64,
+    pda::create_or_top_up(
+        payer,
+        new_account_pda,
+        space,
         program_id,
-    );
-
-    invoke_signed(
-        &instr,
-        &[payer.clone(), new_account_pda.clone()],
-        &[&[payer.key.as_ref(), new_account_seed, &[new_account_bump_seed]]],
+        &[
+            payer.key.as_ref(),
+            new_account_seed,
+            &[new_account_bump_seed],
+        ],
     )?;
 
+    initialize_state(program_id, new_account_pda, payer)?;
+
     msg!("new account: {}", new_account_pda.key);
-    msg!("lamports: {}", lamports);
+    msg!("lamports: {}", new_account_pda.lamports());
 
     Ok(())
 }
@@ -77,36 +87,438 @@ pub fn demo_system_program_transfer_alloc_assign(
     let new_account_seed = &instruction.new_account_seed;
     let new_account_bump_seed = instruction.new_account_bump_seed;
 
-    let space = 1;
-    let rent = Rent::get()?;
-    let lamports = rent.minimum_balance(space);
+    let space = AccountState::LEN;
+
+    pda::top_up_allocate_assign(
+        payer,
+        new_account_pda,
+        space,
+        program_id,
+        &[
+            payer.key.as_ref(),
+            new_account_seed,
+            &[new_account_bump_seed],
+        ],
+    )?;
+
+    initialize_state(program_id, new_account_pda, payer)?;
+
+    msg!("new account: {}", new_account_pda.key);
+    msg!("lamports: {}", new_account_pda.lamports());
+
+    Ok(())
+}
+
+/// Record who paid for a new account, so they can find and close it later.
+///
+/// Does nothing if an earlier call already created the account for the same payer.
+fn initialize_state(
+    program_id: &Pubkey,
+    account: &AccountInfo,
+    payer: &AccountInfo,
+) -> ProgramResult {
+    match state::load(program_id, account) {
+        Ok(state) if state.creator == *payer.key => Ok(()),
+        _ => state::initialize(program_id, account, payer.key, 0),
+    }
+}
+
+/// The accounts of the `*_with_seed` demos,
+/// checked against the `SeedAddress` that derives them.
+struct SeedAddressAccounts<'a, 'b> {
+    payer: &'a AccountInfo<'b>,
+    base_pda: &'a AccountInfo<'b>,
+    address: &'a AccountInfo<'b>,
+}
+
+fn next_seed_address_accounts<'a, 'b>(
+    program_id: &Pubkey,
+    seed_address: &SeedAddress,
+    owner: &Pubkey,
+    accounts: &'a [AccountInfo<'b>],
+) -> Result<SeedAddressAccounts<'a, 'b>, ProgramError> {
+    let account_info_iter = &mut accounts.iter();
+
+    let system_program_account = next_account_info(account_info_iter)?;
+    assert!(system_program::check_id(system_program_account.key));
+
+    let payer = next_account_info(account_info_iter)?;
+    assert!(payer.is_signer);
+    assert!(payer.is_writable);
+
+    let base_pda = next_account_info(account_info_iter)?;
+    assert!(!base_pda.is_signer);
+    let expected_base_pda = Pubkey::create_program_address(
+        &[
+            payer.key.as_ref(),
+            &seed_address.base_seed,
+            &[seed_address.base_bump_seed],
+        ],
+        program_id,
+    )?;
+    assert_eq!(*base_pda.key, expected_base_pda);
+
+    // The address is derived from the base's pubkey, the seed, and the owner
+    // it is meant to have, which the system program checks again.
+    let address = next_account_info(account_info_iter)?;
+    assert!(!address.is_signer);
+    assert!(address.is_writable);
+    let expected_address = Pubkey::create_with_seed(base_pda.key, &seed_address.seed, owner)?;
+    assert_eq!(*address.key, expected_address);
 
-    let transfer_instr = system_instruction::transfer(payer.key, new_account_pda.key, lamports);
+    Ok(SeedAddressAccounts {
+        payer,
+        base_pda,
+        address,
+    })
+}
 
-    let alloc_instr = system_instruction::allocate(new_account_pda.key, space as u64);
+pub fn demo_system_program_create_account_with_seed(
+    program_id: &Pubkey,
+    instruction: DemoSystemProgramCreateAccountWithSeedInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    msg!("demo system program create account with seed");
 
-    let assign_instr = system_instruction::assign(new_account_pda.key, program_id);
+    let seed_address = &instruction.seed_address;
+    let SeedAddressAccounts {
+        payer,
+        base_pda,
+        address,
+    } = next_seed_address_accounts(program_id, seed_address, program_id, accounts)?;
 
+    let space = AccountState::LEN;
+    let rent = Rent::get()?;
+    let lamports = rent.minimum_balance(space);
+
+    let instr = system_instruction::create_account_with_seed(
+        payer.key,
+        address.key,
+        base_pda.key,
+        &seed_address.seed,
+        lamports,
+        space as u64,
+        program_id,
+    );
+
+    // Only the payer and the base sign, not the new account.
     invoke_signed(
-        &transfer_instr,
-        &[payer.clone(), new_account_pda.clone()],
-        &[&[payer.key.as_ref(), new_account_seed, &[new_account_bump_seed]]],
+        &instr,
+        &[payer.clone(), address.clone(), base_pda.clone()],
+        &[&[
+            payer.key.as_ref(),
+            &seed_address.base_seed,
+            &[seed_address.base_bump_seed],
+        ]],
     )?;
 
+    initialize_state(program_id, address, payer)?;
+
+    msg!("new account: {}", address.key);
+    msg!("lamports: {}", lamports);
+
+    Ok(())
+}
+
+pub fn demo_system_program_allocate_with_seed(
+    program_id: &Pubkey,
+    instruction: DemoSystemProgramAllocateWithSeedInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    msg!("demo system program allocate with seed");
+
+    let seed_address = &instruction.seed_address;
+    let SeedAddressAccounts {
+        payer,
+        base_pda,
+        address,
+    } = next_seed_address_accounts(program_id, seed_address, program_id, accounts)?;
+
+    let space = AccountState::LEN;
+    let rent = Rent::get()?;
+    let lamports = rent.minimum_balance(space);
+
+    let transfer_instr = system_instruction::transfer(payer.key, address.key, lamports);
+
+    // Unlike `allocate`, this also assigns the owner,
+    // which must be the owner the address was derived with.
+    let alloc_instr = system_instruction::allocate_with_seed(
+        address.key,
+        base_pda.key,
+        &seed_address.seed,
+        space as u64,
+        program_id,
+    );
+
+    invoke(&transfer_instr, &[payer.clone(), address.clone()])?;
+
     invoke_signed(
         &alloc_instr,
-        &[new_account_pda.clone()],
-        &[&[payer.key.as_ref(), new_account_seed, &[new_account_bump_seed]]],
+        &[address.clone(), base_pda.clone()],
+        &[&[
+            payer.key.as_ref(),
+            &seed_address.base_seed,
+            &[seed_address.base_bump_seed],
+        ]],
     )?;
 
+    assert_eq!(address.owner, program_id);
+    assert_eq!(address.data_len(), space);
+
+    initialize_state(program_id, address, payer)?;
+
+    msg!("new account: {}", address.key);
+    msg!("lamports: {}", lamports);
+
+    Ok(())
+}
+
+pub fn demo_system_program_assign_with_seed(
+    program_id: &Pubkey,
+    instruction: DemoSystemProgramAssignWithSeedInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    msg!("demo system program assign with seed");
+
+    let seed_address = &instruction.seed_address;
+    let SeedAddressAccounts {
+        payer,
+        base_pda,
+        address,
+    } = next_seed_address_accounts(program_id, seed_address, program_id, accounts)?;
+
+    let rent = Rent::get()?;
+    let lamports = rent.minimum_balance(0);
+
+    let transfer_instr = system_instruction::transfer(payer.key, address.key, lamports);
+
+    let assign_instr = system_instruction::assign_with_seed(
+        address.key,
+        base_pda.key,
+        &seed_address.seed,
+        program_id,
+    );
+
+    invoke(&transfer_instr, &[payer.clone(), address.clone()])?;
+
     invoke_signed(
         &assign_instr,
-        &[new_account_pda.clone()],
-        &[&[payer.key.as_ref(), new_account_seed, &[new_account_bump_seed]]],
+        &[address.clone(), base_pda.clone()],
+        &[&[
+            payer.key.as_ref(),
+            &seed_address.base_seed,
+            &[seed_address.base_bump_seed],
+        ]],
     )?;
 
-    msg!("new account: {}", new_account_pda.key);
+    assert_eq!(address.owner, program_id);
+
+    msg!("new account: {}", address.key);
     msg!("lamports: {}", lamports);
 
+    // The account has no data to hold an `AccountState`,
+    // so `close_accounts` could never tell who may reclaim its rent.
+    // Close it here instead: the program owns it now, so it can debit it,
+    // and give it back to the system program, which its empty data allows.
+    let reclaimed = address.lamports();
+    **payer.lamports.borrow_mut() = payer
+        .lamports()
+        .checked_add(reclaimed)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **address.lamports.borrow_mut() = 0;
+    address.assign(&system_program::ID);
+
+    msg!("closed {}, reclaimed {} lamports", address.key, reclaimed);
+
+    Ok(())
+}
+
+pub fn demo_system_program_transfer_with_seed(
+    program_id: &Pubkey,
+    instruction: DemoSystemProgramTransferWithSeedInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    msg!("demo system program transfer with seed");
+
+    // Only accounts owned by the system program can be debited by it,
+    // so this address is derived with the system program as owner.
+    let seed_address = &instruction.seed_address;
+    let SeedAddressAccounts {
+        payer,
+        base_pda,
+        address,
+    } = next_seed_address_accounts(program_id, seed_address, &system_program::ID, accounts)?;
+
+    let rent = Rent::get()?;
+    let lamports = rent.minimum_balance(0);
+
+    let fund_instr = system_instruction::transfer(payer.key, address.key, lamports);
+
+    let transfer_instr = system_instruction::transfer_with_seed(
+        address.key,
+        base_pda.key,
+        seed_address.seed.clone(),
+        &system_program::ID,
+        payer.key,
+        lamports,
+    );
+
+    invoke(&fund_instr, &[payer.clone(), address.clone()])?;
+    msg!(
+        "funded {} with {} lamports",
+        address.key,
+        address.lamports()
+    );
+
+    invoke_signed(
+        &transfer_instr,
+        &[address.clone(), base_pda.clone(), payer.clone()],
+        &[&[
+            payer.key.as_ref(),
+            &seed_address.base_seed,
+            &[seed_address.base_bump_seed],
+        ]],
+    )?;
+    msg!("transferred back, {} lamports left", address.lamports());
+
+    Ok(())
+}
+
+pub fn demo_system_program_nonce(
+    program_id: &Pubkey,
+    instruction: DemoSystemProgramNonceInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    msg!("demo system program nonce: {:?}", instruction.action);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let system_program_account = next_account_info(account_info_iter)?;
+    assert!(system_program::check_id(system_program_account.key));
+
+    let payer = next_account_info(account_info_iter)?;
+    assert!(payer.is_signer);
+    assert!(payer.is_writable);
+
+    let nonce_account = next_account_info(account_info_iter)?;
+    assert!(!nonce_account.is_signer);
+    assert!(nonce_account.is_writable);
+
+    let nonce_authority = next_account_info(account_info_iter)?;
+    assert!(!nonce_authority.is_signer);
+
+    // The nonce instructions still take this deprecated sysvar.
+    let recent_blockhashes = next_account_info(account_info_iter)?;
+    #[allow(deprecated)]
+    let recent_blockhashes_id = sysvar::recent_blockhashes::ID;
+    assert_eq!(*recent_blockhashes.key, recent_blockhashes_id);
+
+    let rent_account = next_account_info(account_info_iter)?;
+    assert!(sysvar::rent::check_id(rent_account.key));
+
+    let nonce_account_seeds: &[&[u8]] = &[
+        payer.key.as_ref(),
+        &instruction.nonce_account_seed,
+        &[instruction.nonce_account_bump_seed],
+    ];
+    let nonce_authority_seeds: &[&[u8]] = &[
+        nonce_account.key.as_ref(),
+        &[instruction.nonce_authority_bump_seed],
+    ];
+
+    assert_eq!(
+        *nonce_account.key,
+        Pubkey::create_program_address(nonce_account_seeds, program_id)?
+    );
+    assert_eq!(
+        *nonce_authority.key,
+        Pubkey::create_program_address(nonce_authority_seeds, program_id)?
+    );
+
+    match instruction.action {
+        NonceAction::Create { lamports } => {
+            // The nonce account is owned by the system program,
+            // and only signs to be created.
+            // The authority PDA is what later signs to use it.
+            let instr = system_instruction::create_account(
+                payer.key,
+                nonce_account.key,
+                lamports,
+                nonce::State::size() as u64,
+                &system_program::ID,
+            );
+            invoke_signed(
+                &instr,
+                &[payer.clone(), nonce_account.clone()],
+                &[nonce_account_seeds],
+            )?;
+
+            // `create_nonce_account` returns this along with `create_account`.
+            let instr = system_instruction::create_nonce_account(
+                payer.key,
+                nonce_account.key,
+                nonce_authority.key,
+                lamports,
+            )
+            .pop()
+            .expect("initialize nonce account instruction");
+            invoke(
+                &instr,
+                &[
+                    nonce_account.clone(),
+                    recent_blockhashes.clone(),
+                    rent_account.clone(),
+                ],
+            )?;
+        }
+        NonceAction::Advance => {
+            let instr =
+                system_instruction::advance_nonce_account(nonce_account.key, nonce_authority.key);
+            invoke_signed(
+                &instr,
+                &[
+                    nonce_account.clone(),
+                    recent_blockhashes.clone(),
+                    nonce_authority.clone(),
+                ],
+                &[nonce_authority_seeds],
+            )?;
+        }
+        NonceAction::Withdraw { lamports } => {
+            let instr = system_instruction::withdraw_nonce_account(
+                nonce_account.key,
+                nonce_authority.key,
+                payer.key,
+                lamports,
+            );
+            invoke_signed(
+                &instr,
+                &[
+                    nonce_account.clone(),
+                    payer.clone(),
+                    recent_blockhashes.clone(),
+                    rent_account.clone(),
+                    nonce_authority.clone(),
+                ],
+                &[nonce_authority_seeds],
+            )?;
+        }
+        NonceAction::Authorize { new_authority } => {
+            let instr = system_instruction::authorize_nonce_account(
+                nonce_account.key,
+                nonce_authority.key,
+                &new_authority,
+            );
+            invoke_signed(
+                &instr,
+                &[nonce_account.clone(), nonce_authority.clone()],
+                &[nonce_authority_seeds],
+            )?;
+        }
+    }
+
+    msg!("nonce account: {}", nonce_account.key);
+    msg!("lamports: {}", nonce_account.lamports());
+
     Ok(())
 }
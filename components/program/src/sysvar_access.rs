@@ -0,0 +1,162 @@
+use crate::checkpoint::{self, checkpoint};
+use common::{ReadSysvarsInstruction, SysvarAccess};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    sysvar::{
+        self, clock::Clock, epoch_schedule::EpochSchedule, rent::Rent, slot_hashes::SlotHashes,
+        slot_history::SlotHistory, stake_history::StakeHistory, Sysvar,
+    },
+};
+
+/// Read every sysvar, either from accounts or via syscalls,
+/// with a compute unit checkpoint after each.
+pub fn read_sysvars(
+    instruction: ReadSysvarsInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    msg!("read sysvars via {:?}", instruction.access);
+
+    match instruction.access {
+        SysvarAccess::Accounts => read_via_accounts(accounts),
+        SysvarAccess::Syscalls => read_via_syscalls(),
+    }
+}
+
+/// Read each sysvar from its account.
+///
+/// The slot hashes, slot history and stake history accounts are large,
+/// and `SlotHistory` alone is bigger than the heap,
+/// so only the fields printed here are read from their raw data.
+fn read_via_accounts(accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    let clock_account = next_account_info(account_info_iter)?;
+    let epoch_schedule_account = next_account_info(account_info_iter)?;
+    let instructions_account = next_account_info(account_info_iter)?;
+    let rent_account = next_account_info(account_info_iter)?;
+    let slot_hashes_account = next_account_info(account_info_iter)?;
+    let slot_history_account = next_account_info(account_info_iter)?;
+    let stake_history_account = next_account_info(account_info_iter)?;
+
+    checkpoint::start();
+
+    let clock = Clock::from_account_info(clock_account)?;
+    checkpoint("clock");
+
+    let epoch_schedule = EpochSchedule::from_account_info(epoch_schedule_account)?;
+    checkpoint("epoch_schedule");
+
+    let current_index = sysvar::instructions::load_current_index_checked(instructions_account)?;
+    checkpoint("instructions");
+
+    let rent = Rent::from_account_info(rent_account)?;
+    checkpoint("rent");
+
+    // A u64 length, then (slot, hash) pairs, most recent first.
+    check_id(slot_hashes_account, &sysvar::slot_hashes::ID)?;
+    let (slot_hashes_len, most_recent_slot) = {
+        let data = slot_hashes_account.try_borrow_data()?;
+        (read_u64(&data, 0)?, read_u64(&data, 8)?)
+    };
+    checkpoint("slot_hashes");
+
+    // `next_slot` is the last field.
+    check_id(slot_history_account, &sysvar::slot_history::ID)?;
+    let next_slot = {
+        let data = slot_history_account.try_borrow_data()?;
+        let offset = data
+            .len()
+            .checked_sub(8)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        read_u64(&data, offset)?
+    };
+    checkpoint("slot_history");
+
+    // A u64 length, then (epoch, effective, activating, deactivating), most recent first.
+    check_id(stake_history_account, &sysvar::stake_history::ID)?;
+    let (stake_history_len, latest_epoch) = {
+        let data = stake_history_account.try_borrow_data()?;
+        let len = read_u64(&data, 0)?;
+        let latest_epoch = if len > 0 {
+            Some(read_u64(&data, 8)?)
+        } else {
+            None
+        };
+        (len, latest_epoch)
+    };
+    checkpoint("stake_history");
+
+    msg!("clock: {:?}", clock);
+    msg!("epoch_schedule: {:?}", epoch_schedule);
+    msg!("instructions current index: {}", current_index);
+    msg!("rent: {:?}", rent);
+    msg!(
+        "slot_hashes: {} entries, most recent slot {}",
+        slot_hashes_len,
+        most_recent_slot
+    );
+    msg!("slot_history next slot: {}", next_slot);
+    msg!(
+        "stake_history: {} entries, latest epoch {:?}",
+        stake_history_len,
+        latest_epoch
+    );
+
+    Ok(())
+}
+
+/// Read each sysvar with `Sysvar::get`.
+///
+/// Only some sysvars have a syscall.
+/// The rest need their account passed to the instruction,
+/// which is logged as `unsupported: <sysvar>: <reason>`.
+fn read_via_syscalls() -> ProgramResult {
+    checkpoint::start();
+
+    let clock = Clock::get()?;
+    checkpoint("clock");
+
+    let epoch_schedule = EpochSchedule::get()?;
+    checkpoint("epoch_schedule");
+
+    let rent = Rent::get()?;
+    checkpoint("rent");
+
+    // Logged after the checkpoints so the logging isn't counted.
+    msg!("clock: {:?}", clock);
+    msg!("epoch_schedule: {:?}", epoch_schedule);
+    msg!("rent: {:?}", rent);
+
+    msg!("unsupported: instructions: the instructions sysvar has no syscall");
+    if let Err(e) = SlotHashes::get() {
+        msg!("unsupported: slot_hashes: {:?}", e);
+    }
+    if let Err(e) = SlotHistory::get() {
+        msg!("unsupported: slot_history: {:?}", e);
+    }
+    if let Err(e) = StakeHistory::get() {
+        msg!("unsupported: stake_history: {:?}", e);
+    }
+
+    Ok(())
+}
+
+fn check_id(account: &AccountInfo, id: &solana_program::pubkey::Pubkey) -> ProgramResult {
+    if account.key != id {
+        msg!("expected sysvar {}, got {}", id, account.key);
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    Ok(())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidAccountData)?;
+
+    Ok(u64::from_le_bytes(bytes.try_into().expect("8 bytes")))
+}
@@ -1,9 +1,9 @@
+use crate::checkpoint::{self, checkpoint};
 use borsh::de::BorshDeserialize;
 use common::{CustomInstruction, PrintSysvarsInstruction, PrintSysvarsSection};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
-    log::sol_log_compute_units,
     msg,
     sysvar::Sysvar,
 };
@@ -31,113 +31,207 @@ pub fn print_sysvars(
     let stake_history_account = next_account_info(account_info_iter)?;
 
     msg!("section {:?}", instruction.section);
-    sol_log_compute_units();
+    checkpoint::start();
+
+    let sysvars = SysvarAccounts {
+        clock_account,
+        epoch_schedule_account,
+        instructions_account,
+        rent_account,
+        slot_hashes_account,
+        slot_history_account,
+        stake_history_account,
+    };
 
     match instruction.section {
-        PrintSysvarsSection::One => {
-            {
-                use solana_program::sysvar::clock::{self, Clock};
+        PrintSysvarsSection::One => print_section_one(&sysvars, instruction_data)?,
+        PrintSysvarsSection::Two => print_section_two(&sysvars)?,
+        PrintSysvarsSection::Three => print_section_three(&sysvars)?,
+        PrintSysvarsSection::Dynamic {
+            compute_units,
+            base_units,
+            section_units,
+        } => print_sections_dynamic(
+            &sysvars,
+            instruction_data,
+            compute_units,
+            base_units,
+            section_units,
+        )?,
+    }
 
-                assert!(clock::check_id(clock_account.key));
-                assert_eq!(*clock_account.key, clock::ID);
+    Ok(())
+}
 
-                let clock_from_account = Clock::from_account_info(clock_account)?;
-                let clock_from_sysvar = Clock::get()?;
+struct SysvarAccounts<'a, 'b> {
+    clock_account: &'a AccountInfo<'b>,
+    epoch_schedule_account: &'a AccountInfo<'b>,
+    instructions_account: &'a AccountInfo<'b>,
+    rent_account: &'a AccountInfo<'b>,
+    slot_hashes_account: &'a AccountInfo<'b>,
+    slot_history_account: &'a AccountInfo<'b>,
+    stake_history_account: &'a AccountInfo<'b>,
+}
 
-                assert_eq!(clock_from_account, clock_from_sysvar);
+/// Print every section that fits in the requested compute budget.
+///
+/// There is no syscall for a program to ask how many compute units it has left,
+/// only `sol_log_compute_units`, which logs them for the client to read.
+/// So the program plans with the section costs the client measured from those logs.
+fn print_sections_dynamic(
+    sysvars: &SysvarAccounts,
+    instruction_data: &[u8],
+    compute_units: u32,
+    base_units: u32,
+    section_units: [u32; 3],
+) -> ProgramResult {
+    let sections = [
+        PrintSysvarsSection::One,
+        PrintSysvarsSection::Two,
+        PrintSysvarsSection::Three,
+    ];
+
+    let mut remaining = compute_units.saturating_sub(base_units);
+
+    for (section, units) in sections.into_iter().zip(section_units) {
+        if units > remaining {
+            msg!(
+                "skipping section {:?}: measured {} units, {} remaining",
+                section,
+                units,
+                remaining
+            );
+            continue;
+        }
 
-                msg!("clock: {:#?}", clock_from_account);
-                sol_log_compute_units();
-            }
+        msg!("section {:?}", section);
+        match section {
+            PrintSysvarsSection::One => print_section_one(sysvars, instruction_data)?,
+            PrintSysvarsSection::Two => print_section_two(sysvars)?,
+            PrintSysvarsSection::Three => print_section_three(sysvars)?,
+            PrintSysvarsSection::Dynamic { .. } => unreachable!(),
+        }
 
-            {
-                use solana_program::sysvar::epoch_schedule::{self, EpochSchedule};
+        remaining -= units;
+    }
 
-                assert!(epoch_schedule::check_id(epoch_schedule_account.key));
-                assert_eq!(*epoch_schedule_account.key, epoch_schedule::ID);
+    Ok(())
+}
 
-                let epoch_schedule_from_account =
-                    EpochSchedule::from_account_info(epoch_schedule_account)?;
-                let epoch_schedule_from_sysvar = EpochSchedule::get()?;
+fn print_section_one(sysvars: &SysvarAccounts, instruction_data: &[u8]) -> ProgramResult {
+    let clock_account = sysvars.clock_account;
+    let epoch_schedule_account = sysvars.epoch_schedule_account;
+    let instructions_account = sysvars.instructions_account;
 
-                assert_eq!(epoch_schedule_from_account, epoch_schedule_from_sysvar);
+    {
+        use solana_program::sysvar::clock::{self, Clock};
 
-                msg!("epoch_schedule: {:#?}", epoch_schedule_from_sysvar);
-                sol_log_compute_units();
-            }
+        assert!(clock::check_id(clock_account.key));
+        assert_eq!(*clock_account.key, clock::ID);
 
-            {
-                use solana_program::sysvar::instructions;
+        let clock_from_account = Clock::from_account_info(clock_account)?;
+        let clock_from_sysvar = Clock::get()?;
 
-                assert!(instructions::check_id(instructions_account.key));
-                assert_eq!(*instructions_account.key, instructions::ID);
+        assert_eq!(clock_from_account, clock_from_sysvar);
 
-                let current_index = instructions::load_current_index_checked(instructions_account)?;
-                let instructions_from_account = instructions::load_instruction_at_checked(
-                    current_index.into(),
-                    instructions_account,
-                )?;
+        msg!("clock: {:#?}", clock_from_account);
+        checkpoint("clock");
+    }
 
-                assert_eq!(instructions_from_account.data, instruction_data);
+    {
+        use solana_program::sysvar::epoch_schedule::{self, EpochSchedule};
 
-                let mut instruction_data = instruction_data;
-                let deserialized_instruction_data =
-                    CustomInstruction::deserialize(&mut instruction_data)?;
+        assert!(epoch_schedule::check_id(epoch_schedule_account.key));
+        assert_eq!(*epoch_schedule_account.key, epoch_schedule::ID);
 
-                msg!(
-                    "deserialized_instruction_data: {:#?}",
-                    deserialized_instruction_data
-                );
-                sol_log_compute_units();
-            }
-        }
-        PrintSysvarsSection::Two => {
-            {
-                use solana_program::sysvar::rent::{self, Rent};
+        let epoch_schedule_from_account = EpochSchedule::from_account_info(epoch_schedule_account)?;
+        let epoch_schedule_from_sysvar = EpochSchedule::get()?;
 
-                assert!(rent::check_id(rent_account.key));
-                assert_eq!(*rent_account.key, rent::ID);
+        assert_eq!(epoch_schedule_from_account, epoch_schedule_from_sysvar);
 
-                let rent_from_account = Rent::from_account_info(rent_account)?;
-                let rent_from_sysvar = Rent::get()?;
+        msg!("epoch_schedule: {:#?}", epoch_schedule_from_sysvar);
+        checkpoint("epoch_schedule");
+    }
 
-                assert_eq!(rent_from_account, rent_from_sysvar);
+    {
+        use solana_program::sysvar::instructions;
 
-                msg!("rent: {:#?}", rent_from_account);
-                sol_log_compute_units();
-            }
+        assert!(instructions::check_id(instructions_account.key));
+        assert_eq!(*instructions_account.key, instructions::ID);
 
-            {
-                use solana_program::sysvar::slot_hashes;
+        let current_index = instructions::load_current_index_checked(instructions_account)?;
+        let instructions_from_account =
+            instructions::load_instruction_at_checked(current_index.into(), instructions_account)?;
 
-                assert!(slot_hashes::check_id(slot_hashes_account.key));
-                assert_eq!(*slot_hashes_account.key, slot_hashes::ID);
+        assert_eq!(instructions_from_account.data, instruction_data);
 
-                msg!("slot_hashes: {:#?}", slot_hashes_account);
-                sol_log_compute_units();
-            }
-        }
-        PrintSysvarsSection::Three => {
-            {
-                use solana_program::sysvar::slot_history;
+        let mut instruction_data = instruction_data;
+        let deserialized_instruction_data = CustomInstruction::deserialize(&mut instruction_data)?;
 
-                assert!(slot_history::check_id(slot_history_account.key));
-                assert_eq!(*slot_history_account.key, slot_history::ID);
+        msg!(
+            "deserialized_instruction_data: {:#?}",
+            deserialized_instruction_data
+        );
+        checkpoint("instructions");
+    }
 
-                msg!("slot_history: {:#?}", slot_history_account);
-                sol_log_compute_units();
-            }
+    Ok(())
+}
 
-            {
-                use solana_program::sysvar::stake_history;
+fn print_section_two(sysvars: &SysvarAccounts) -> ProgramResult {
+    let rent_account = sysvars.rent_account;
+    let slot_hashes_account = sysvars.slot_hashes_account;
 
-                assert!(stake_history::check_id(stake_history_account.key));
-                assert_eq!(*stake_history_account.key, stake_history::ID);
+    {
+        use solana_program::sysvar::rent::{self, Rent};
 
-                msg!("stake_history: {:#?}", stake_history_account);
-                sol_log_compute_units();
-            }
-        }
+        assert!(rent::check_id(rent_account.key));
+        assert_eq!(*rent_account.key, rent::ID);
+
+        let rent_from_account = Rent::from_account_info(rent_account)?;
+        let rent_from_sysvar = Rent::get()?;
+
+        assert_eq!(rent_from_account, rent_from_sysvar);
+
+        msg!("rent: {:#?}", rent_from_account);
+        checkpoint("rent");
+    }
+
+    {
+        use solana_program::sysvar::slot_hashes;
+
+        assert!(slot_hashes::check_id(slot_hashes_account.key));
+        assert_eq!(*slot_hashes_account.key, slot_hashes::ID);
+
+        msg!("slot_hashes: {:#?}", slot_hashes_account);
+        checkpoint("slot_hashes");
+    }
+
+    Ok(())
+}
+
+fn print_section_three(sysvars: &SysvarAccounts) -> ProgramResult {
+    let slot_history_account = sysvars.slot_history_account;
+    let stake_history_account = sysvars.stake_history_account;
+
+    {
+        use solana_program::sysvar::slot_history;
+
+        assert!(slot_history::check_id(slot_history_account.key));
+        assert_eq!(*slot_history_account.key, slot_history::ID);
+
+        msg!("slot_history: {:#?}", slot_history_account);
+        checkpoint("slot_history");
+    }
+
+    {
+        use solana_program::sysvar::stake_history;
+
+        assert!(stake_history::check_id(stake_history_account.key));
+        assert_eq!(*stake_history_account.key, stake_history::ID);
+
+        msg!("stake_history: {:#?}", stake_history_account);
+        checkpoint("stake_history");
     }
 
     Ok(())
@@ -0,0 +1,25 @@
+#!/bin/sh
+# Build `program` with each demo feature on its own, with none, and with all,
+# and print the size of the resulting `.so`.
+#
+# Run from the repository root: ./scripts/size-report.sh
+
+set -e
+
+manifest=components/program/Cargo.toml
+so=target/deploy/program.so
+
+build() {
+    label=$1
+    shift
+    cargo build-bpf --manifest-path "$manifest" "$@" > /dev/null 2>&1
+    printf "%-24s %10s\n" "$label" "$(wc -c < "$so" | tr -d ' ')"
+}
+
+printf "%-24s %10s\n" "features" "bytes"
+
+build "none" --no-default-features
+for feature in sysvars secp256k1 ed25519 invoke sysprog state introspect; do
+    build "$feature" --no-default-features --features "$feature"
+done
+build "all"